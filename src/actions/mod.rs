@@ -0,0 +1,437 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::db::ClipboardItem;
+use crate::kind::Kind;
+
+// Qué produce una acción: o abre algo fuera de ropias o genera un texto nuevo,
+// que se copia como un elemento más del historial.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Opened,
+    Text(String),
+}
+
+// Una acción que se ofrece según la clase del elemento.
+#[derive(Debug)]
+pub struct Action {
+    pub name: &'static str,
+    pub label: &'static str,
+    applies: fn(&ClipboardItem) -> bool,
+    run: fn(&ClipboardItem) -> Result<Outcome, Box<dyn Error>>,
+}
+
+impl Action {
+    pub fn applies(&self, item: &ClipboardItem) -> bool {
+        !item.secret && (self.applies)(item)
+    }
+
+    pub fn run(&self, item: &ClipboardItem) -> Result<Outcome, Box<dyn Error>> {
+        if !self.applies(item) {
+            return Err(format!("action {} does not apply to clipboard item {}", self.name, item.id).into());
+        }
+        (self.run)(item)
+    }
+}
+
+impl PartialEq for Action {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label)
+    }
+}
+
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "open",
+        label: "Open link",
+        applies: |item| is(item, Kind::Url) || is(item, Kind::Email),
+        run: open,
+    },
+    Action {
+        name: "reveal",
+        label: "Open folder",
+        applies: |item| is(item, Kind::Path) || item.content_type == "files",
+        run: reveal,
+    },
+    Action {
+        name: "json-pretty",
+        label: "Format JSON",
+        applies: |item| is(item, Kind::Json),
+        run: |item| json(item, true),
+    },
+    Action {
+        name: "json-minify",
+        label: "Minify JSON",
+        applies: |item| is(item, Kind::Json),
+        run: |item| json(item, false),
+    },
+    Action {
+        name: "base64-encode",
+        label: "Encode Base64",
+        applies: |item| item.content_type == "text",
+        run: |item| Ok(Outcome::Text(base64_encode(item.content.as_bytes()))),
+    },
+    Action {
+        name: "base64-decode",
+        label: "Decode Base64",
+        applies: |item| item.content_type == "text" && base64_text(&item.content).is_some(),
+        run: |item| {
+            let text = base64_text(&item.content).ok_or("the content is not Base64-encoded text")?;
+            Ok(Outcome::Text(text))
+        },
+    },
+    Action {
+        name: "url-decode",
+        label: "Decode URL",
+        applies: |item| item.content_type == "text" && url_decode(&item.content) != item.content,
+        run: |item| Ok(Outcome::Text(url_decode(&item.content))),
+    },
+    Action {
+        name: "color-hex",
+        label: "Color as hex",
+        applies: |item| is(item, Kind::Color) && !item.content.trim().starts_with('#'),
+        run: |item| Ok(Outcome::Text(color(item)?.hex())),
+    },
+    Action {
+        name: "color-rgb",
+        label: "Color as RGB",
+        applies: |item| is(item, Kind::Color) && !item.content.trim().to_lowercase().starts_with("rgb"),
+        run: |item| Ok(Outcome::Text(color(item)?.rgb())),
+    },
+    Action {
+        name: "color-hsl",
+        label: "Color as HSL",
+        applies: |item| is(item, Kind::Color) && !item.content.trim().to_lowercase().starts_with("hsl"),
+        run: |item| Ok(Outcome::Text(color(item)?.hsl())),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Action> {
+    ACTIONS.iter().find(|action| action.name == name)
+}
+
+pub fn available(item: &ClipboardItem) -> Vec<&'static Action> {
+    ACTIONS.iter().filter(|action| action.applies(item)).collect()
+}
+
+fn is(item: &ClipboardItem, kind: Kind) -> bool {
+    item.kind == Some(kind)
+}
+
+// xdg-open elige la aplicación configurada en el escritorio y no bloquea.
+fn xdg_open(target: &str) -> Result<Outcome, Box<dyn Error>> {
+    Command::new("xdg-open")
+        .arg(target)
+        .spawn()
+        .map_err(|err| format!("cannot run xdg-open: {err}"))?;
+    Ok(Outcome::Opened)
+}
+
+fn open(item: &ClipboardItem) -> Result<Outcome, Box<dyn Error>> {
+    let target = item.content.trim();
+    match item.kind {
+        Some(Kind::Email) if !target.to_lowercase().starts_with("mailto:") => xdg_open(&format!("mailto:{target}")),
+        _ if target.starts_with("www.") => xdg_open(&format!("https://{target}")),
+        _ => xdg_open(target),
+    }
+}
+
+// Abre la carpeta que contiene la ruta, o la ruta misma si es una carpeta.
+fn reveal(item: &ClipboardItem) -> Result<Outcome, Box<dyn Error>> {
+    let first = item.content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+    let path = match first.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME").map_or_else(|| PathBuf::from(first), |home| Path::new(&home).join(rest)),
+        None => PathBuf::from(first),
+    };
+    let folder = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent().ok_or_else(|| format!("{} has no parent folder", path.display()))?
+    };
+    if !folder.is_dir() {
+        return Err(format!("folder {} does not exist", folder.display()).into());
+    }
+    xdg_open(&folder.to_string_lossy())
+}
+
+fn json(item: &ClipboardItem, pretty: bool) -> Result<Outcome, Box<dyn Error>> {
+    let value: serde_json::Value = serde_json::from_str(item.content.trim())?;
+    Ok(Outcome::Text(if pretty {
+        serde_json::to_string_pretty(&value)?
+    } else {
+        serde_json::to_string(&value)?
+    }))
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Acepta también el alfabeto para URLs y el relleno opcional.
+pub fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim().trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut group = 0u32;
+    let mut bits = 0;
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        group = group << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+    // Un solo carácter de más no puede venir de una codificación válida.
+    (bits < 6).then_some(bytes)
+}
+
+// Solo se ofrece decodificar lo que da texto legible, para no confundir palabras
+// sueltas con Base64.
+fn base64_text(content: &str) -> Option<String> {
+    let content = content.trim();
+    if content.len() < 8 || content.len() > 1 << 20 || content.contains(char::is_whitespace) {
+        return None;
+    }
+    let text = String::from_utf8(base64_decode(content)?).ok()?;
+    (!text.chars().any(|c| c.is_control() && !c.is_whitespace())).then_some(text)
+}
+
+// Las secuencias `%XX` inválidas se dejan como están.
+pub fn url_decode(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.filter(|_| bytes[i] == b'%').and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub alpha: f32,
+}
+
+fn color(item: &ClipboardItem) -> Result<Color, Box<dyn Error>> {
+    Color::parse(&item.content).ok_or_else(|| format!("cannot read the color {:?}", item.content.trim()).into())
+}
+
+impl Color {
+    // "#rgb", "#rrggbb" (con alfa opcional), "rgb(…)", "rgba(…)", "hsl(…)" y "hsla(…)".
+    pub fn parse(color: &str) -> Option<Color> {
+        let color = color.trim().to_lowercase();
+        if let Some(hex) = color.strip_prefix('#') {
+            let digits: Vec<u8> = match hex.len() {
+                3 | 4 => hex
+                    .chars()
+                    .map(|c| c.to_digit(16).map(|digit| digit as u8 * 17))
+                    .collect::<Option<_>>()?,
+                6 | 8 => (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                    .collect::<Option<_>>()?,
+                _ => return None,
+            };
+            return Some(Color {
+                r: digits[0],
+                g: digits[1],
+                b: digits[2],
+                alpha: digits.get(3).map_or(1.0, |alpha| *alpha as f32 / 255.0),
+            });
+        }
+        let (function, arguments) = color.split_once('(')?;
+        let arguments: Vec<&str> = arguments
+            .strip_suffix(')')?
+            .split([',', ' ', '/'])
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        if !(3..=4).contains(&arguments.len()) {
+            return None;
+        }
+        let alpha = match arguments.get(3) {
+            Some(alpha) => percent(alpha, 1.0)?,
+            None => 1.0,
+        };
+        let channel = |argument: &str| Some(percent(argument, 255.0)?.round().clamp(0.0, 255.0) as u8);
+        match function.trim() {
+            "rgb" | "rgba" => Some(Color {
+                r: channel(arguments[0])?,
+                g: channel(arguments[1])?,
+                b: channel(arguments[2])?,
+                alpha,
+            }),
+            "hsl" | "hsla" => {
+                let hue = arguments[0].trim_end_matches("deg").parse::<f32>().ok()?;
+                let saturation = percent(arguments[1], 1.0)?;
+                let lightness = percent(arguments[2], 1.0)?;
+                let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+                Some(Color { r, g, b, alpha })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn hex(&self) -> String {
+        let hex = format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b);
+        if self.alpha < 1.0 {
+            format!("{hex}{:02x}", (self.alpha * 255.0).round() as u8)
+        } else {
+            hex
+        }
+    }
+
+    pub fn rgb(&self) -> String {
+        if self.alpha < 1.0 {
+            format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, round(self.alpha, 2))
+        } else {
+            format!("rgb({}, {}, {})", self.r, self.g, self.b)
+        }
+    }
+
+    pub fn hsl(&self) -> String {
+        let (hue, saturation, lightness) = rgb_to_hsl(self.r, self.g, self.b);
+        let hsl = format!("{}, {}%, {}%", hue.round(), round(saturation * 100.0, 1), round(lightness * 100.0, 1));
+        if self.alpha < 1.0 {
+            format!("hsla({hsl}, {})", round(self.alpha, 2))
+        } else {
+            format!("hsl({hsl})")
+        }
+    }
+}
+
+// Un número o un porcentaje de `max`.
+fn percent(argument: &str, max: f32) -> Option<f32> {
+    match argument.strip_suffix('%') {
+        Some(percent) => Some(percent.parse::<f32>().ok()? / 100.0 * max),
+        None => argument.parse().ok(),
+    }
+}
+
+fn round(value: f32, decimals: i32) -> f32 {
+    let factor = 10f32.powi(decimals);
+    (value * factor).round() / factor
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation, lightness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(item: &ClipboardItem) -> Vec<&'static str> {
+        available(item).iter().map(|action| action.name).collect()
+    }
+
+    #[test]
+    fn actions_by_kind() {
+        assert!(names(&ClipboardItem::test_text("https://example.com")).contains(&"open"));
+        assert!(names(&ClipboardItem::test_text("{\"a\": 1}")).contains(&"json-pretty"));
+        assert_eq!(names(&ClipboardItem::test_text("#ff8800")), ["base64-encode", "color-rgb", "color-hsl"]);
+        let mut secret = ClipboardItem::test_text("https://example.com/?token=abc");
+        secret.secret = true;
+        assert!(names(&secret).is_empty());
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(base64_encode("canción".as_bytes()), "Y2FuY2nDs24=");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_decode("Y2FuY2nDs24=").unwrap(), "canción".as_bytes());
+        assert_eq!(base64_decode("YWI").unwrap(), b"ab");
+        assert_eq!(base64_decode("no es base64"), None);
+        assert_eq!(base64_text("aG9sYSBtdW5kbw=="), Some("hola mundo".to_string()));
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(url_decode("a%20b%C3%B1%zz%"), "a bñ%zz%");
+    }
+
+    #[test]
+    fn colors() {
+        let orange = Color::parse("#ff8800").unwrap();
+        assert_eq!(orange.rgb(), "rgb(255, 136, 0)");
+        assert_eq!(orange.hsl(), "hsl(32, 100%, 50%)");
+        assert_eq!(Color::parse("hsl(32deg 100% 50%)").unwrap().hex(), "#ff8800");
+        assert_eq!(Color::parse("rgba(0, 0, 255, 0.5)").unwrap().hex(), "#0000ff80");
+        assert_eq!(Color::parse("#fff").unwrap().hsl(), "hsl(0, 0%, 100%)");
+    }
+}
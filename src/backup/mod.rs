@@ -0,0 +1,569 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::db::{self, ClipboardItem, NewClipboardItem};
+use crate::preview;
+
+// Nombre de los metadatos dentro de un archivo tar. Van primero, para poder
+// importar el archivo leyéndolo de una sola pasada.
+const METADATA: &str = "metadata.jsonl";
+
+// Formatos de exportación. JSONL y CSV solo llevan texto y listas de rutas; el
+// archivo tar lleva también las imágenes y los archivos de las listas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Jsonl,
+    Csv,
+    Archive,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "jsonl" | "json" => Some(Format::Jsonl),
+            "csv" => Some(Format::Csv),
+            "tar" | "archive" => Some(Format::Archive),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension().and_then(|extension| Format::parse(&extension.to_string_lossy()))
+    }
+}
+
+// Un elemento exportado, con todo lo necesario para recrearlo en otra máquina.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    #[serde(rename = "type", default = "default_content_type")]
+    pub content_type: String,
+    #[serde(default = "default_mime")]
+    pub mime: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub source_app: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(default, with = "comma_list")]
+    pub tags: Vec<String>,
+    // Ruta del contenido binario dentro del archivo tar. En las listas de archivos
+    // es una carpeta con una subcarpeta por línea: "files/<hash>/0/foto.png".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+    pub content: String,
+}
+
+// Las etiquetas van separadas por comas, para que en CSV ocupen una sola columna.
+mod comma_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(tags: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&tags.join(","))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+        let tags = String::deserialize(deserializer)?;
+        Ok(tags.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(String::from).collect())
+    }
+}
+
+fn default_content_type() -> String {
+    "text".to_string()
+}
+
+fn default_mime() -> String {
+    "text/plain".to_string()
+}
+
+impl From<ClipboardItem> for Record {
+    fn from(item: ClipboardItem) -> Self {
+        Record {
+            content_type: item.content_type,
+            mime: item.mime,
+            created_at: item.created_at,
+            favorite: item.favorite,
+            source_app: item.source_app,
+            secret: item.secret,
+            tags: item.tags,
+            blob: None,
+            content: item.content,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub exported: usize,
+    // Imágenes que JSONL y CSV no pueden llevar.
+    pub skipped_images: usize,
+    pub skipped_secrets: usize,
+    // Archivos de las listas que ya no existen.
+    pub missing_files: usize,
+}
+
+impl fmt::Display for ExportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exported {} items", self.exported)?;
+        if self.skipped_images > 0 {
+            write!(f, ", skipped {} images (use a .tar archive to include them)", self.skipped_images)?;
+        }
+        if self.skipped_secrets > 0 {
+            write!(f, ", skipped {} secrets (use --secrets to include them)", self.skipped_secrets)?;
+        }
+        if self.missing_files > 0 {
+            write!(f, ", {} listed files no longer exist", self.missing_files)?;
+        }
+        Ok(())
+    }
+}
+
+// Los secretos solo se exportan si se pide, porque el archivo queda sin cifrar.
+pub fn export(path: &Path, format: Format, secrets: bool) -> Result<ExportReport, Box<dyn Error>> {
+    let mut report = ExportReport::default();
+    let file = BufWriter::new(File::create(path)?);
+    match format {
+        Format::Jsonl => {
+            let mut file = file;
+            db::for_each_clipboard_item(|item, _| {
+                if let Some(record) = exported(item, secrets, &mut report) {
+                    serde_json::to_writer(&mut file, &record)?;
+                    file.write_all(b"\n")?;
+                }
+                Ok(())
+            })?;
+            file.flush()?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            db::for_each_clipboard_item(|item, _| {
+                if let Some(record) = exported(item, secrets, &mut report) {
+                    writer.serialize(record)?;
+                }
+                Ok(())
+            })?;
+            writer.flush()?;
+        }
+        // Una pasada escribe los metadatos y otra agrega las imágenes y los archivos.
+        Format::Archive => {
+            let mut archive = tar::Builder::new(file);
+            let mut metadata = Vec::new();
+            db::for_each_clipboard_item(|item, data| {
+                if item.secret && !secrets {
+                    report.skipped_secrets += 1;
+                    return Ok(());
+                }
+                let mut record = Record::from(item);
+                record.blob = blob_name(&record, data.as_deref());
+                serde_json::to_writer(&mut metadata, &record)?;
+                metadata.push(b'\n');
+                report.exported += 1;
+                Ok(())
+            })?;
+            append(&mut archive, METADATA, &metadata)?;
+            db::for_each_clipboard_item(|item, data| {
+                if item.secret && !secrets {
+                    return Ok(());
+                }
+                let record = Record::from(item);
+                match (blob_name(&record, data.as_deref()), data) {
+                    (Some(blob), Some(data)) => append(&mut archive, &blob, &data)?,
+                    (Some(blob), None) => report.missing_files += append_files(&mut archive, &blob, &record.content)?,
+                    (None, _) => {}
+                }
+                Ok(())
+            })?;
+            archive.into_inner()?.flush()?;
+        }
+    }
+    Ok(report)
+}
+
+fn blob_name(record: &Record, data: Option<&[u8]>) -> Option<String> {
+    let hash = db::content_hash(&record.content_type, &record.content, data);
+    match (record.content_type.as_str(), data) {
+        ("files", _) => Some(format!("files/{hash}")),
+        (_, Some(_)) => Some(format!("blobs/{hash}")),
+        (_, None) => None,
+    }
+}
+
+// Devuelve cuántos archivos de la lista ya no existen.
+fn append_files(archive: &mut tar::Builder<impl Write>, blob: &str, paths: &str) -> Result<usize, Box<dyn Error>> {
+    let mut missing = 0;
+    for (i, path) in paths.lines().map(Path::new).enumerate() {
+        let Some(name) = path.file_name() else {
+            missing += 1;
+            continue;
+        };
+        let name = format!("{blob}/{i}/{}", name.to_string_lossy());
+        let appended = if path.is_dir() {
+            archive.append_dir_all(&name, path)
+        } else if path.is_file() {
+            archive.append_path_with_name(path, &name)
+        } else {
+            missing += 1;
+            continue;
+        };
+        appended.map_err(|err| format!("cannot archive {}: {err}", path.display()))?;
+    }
+    Ok(missing)
+}
+
+fn exported(item: ClipboardItem, secrets: bool, report: &mut ExportReport) -> Option<Record> {
+    if item.content_type == "image" {
+        report.skipped_images += 1;
+        return None;
+    }
+    if item.secret && !secrets {
+        report.skipped_secrets += 1;
+        return None;
+    }
+    report.exported += 1;
+    Some(Record::from(item))
+}
+
+fn append(archive: &mut tar::Builder<impl Write>, name: &str, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_cksum();
+    archive.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    // Ya estaban, con los mismos datos.
+    pub duplicates: usize,
+    // Ya estaban pero con otra fecha, otro origen u otra marca de favorito.
+    pub conflicts: Vec<String>,
+    // Registros que no se pudieron leer.
+    pub invalid: Vec<String>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "imported {} items, skipped {} duplicates, {} conflicts, {} invalid",
+            self.imported,
+            self.duplicates,
+            self.conflicts.len(),
+            self.invalid.len()
+        )?;
+        for conflict in &self.conflicts {
+            write!(f, "\nconflict: {conflict}")?;
+        }
+        for invalid in &self.invalid {
+            write!(f, "\ninvalid: {invalid}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn import(path: &Path, format: Format) -> Result<ImportReport, Box<dyn Error>> {
+    let mut report = ImportReport::default();
+    match format {
+        Format::Jsonl => {
+            for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Record>(&line) {
+                    Ok(record) => merge(&record, None, &mut report)?,
+                    Err(err) => report.invalid.push(format!("line {}: {err}", i + 1)),
+                }
+            }
+        }
+        Format::Csv => {
+            for (i, record) in csv::Reader::from_path(path)?.deserialize::<Record>().enumerate() {
+                match record {
+                    Ok(record) => merge(&record, None, &mut report)?,
+                    Err(err) => report.invalid.push(format!("row {}: {err}", i + 1)),
+                }
+            }
+        }
+        Format::Archive => import_archive(path, &mut report)?,
+    }
+    Ok(report)
+}
+
+// Se lee entrada por entrada: primero los metadatos y después cada imagen se
+// importa al llegar y los archivos se extraen en `db::data_dir`.
+fn import_archive(path: &Path, report: &mut ImportReport) -> Result<(), Box<dyn Error>> {
+    let mut archive = tar::Archive::new(BufReader::new(File::open(path)?));
+    let mut entries = archive.entries()?;
+    let mut metadata = entries.next().ok_or("the archive is empty")??;
+    if metadata.path()?.as_ref() != Path::new(METADATA) {
+        return Err(format!("the archive does not start with {METADATA}").into());
+    }
+    // Imágenes por su nombre en el archivo, a la espera de su contenido.
+    let mut images: HashMap<String, Vec<(usize, Record)>> = HashMap::new();
+    let mut files = Vec::new();
+    for (i, line) in BufReader::new(&mut metadata).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match serde_json::from_str::<Record>(&line) {
+            Ok(record) => record,
+            Err(err) => {
+                report.invalid.push(format!("metadata line {}: {err}", i + 1));
+                continue;
+            }
+        };
+        match record.blob.clone() {
+            None => merge(&record, None, report)?,
+            Some(blob) if record.content_type != "files" => images.entry(blob).or_default().push((i + 1, record)),
+            Some(blob) if !is_files_blob(&blob) => report.invalid.push(format!("metadata line {}: bad file folder {blob:?}", i + 1)),
+            // Si ya está, con las rutas originales, no hace falta extraer nada.
+            Some(_) if db::find_by_hash(&db::content_hash("files", &record.content, None))?.is_some() => merge(&record, None, report)?,
+            Some(_) => files.push(record),
+        }
+    }
+    drop(metadata);
+
+    let dir = db::data_dir();
+    fs::create_dir_all(&dir)?;
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if let Some(records) = images.remove(&name) {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            for (_, record) in records {
+                merge(&record, Some(&data), report)?;
+            }
+        } else if files.iter().any(|record| record.blob.as_ref().is_some_and(|blob| name.starts_with(&format!("{blob}/")))) {
+            entry.unpack_in(&dir)?;
+        }
+    }
+    for (blob, records) in images {
+        for (line, _) in records {
+            report.invalid.push(format!("metadata line {line}: missing {blob}"));
+        }
+    }
+    for mut record in files {
+        record.content = extracted_paths(&record, &dir);
+        merge(&record, None, report)?;
+    }
+    Ok(())
+}
+
+// "files/<hash>", sin nada que permita salir de `db::data_dir`.
+fn is_files_blob(blob: &str) -> bool {
+    blob.strip_prefix("files/")
+        .is_some_and(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// Cada ruta de la lista pasa a apuntar a su copia extraída, si venía en el archivo.
+fn extracted_paths(record: &Record, dir: &Path) -> String {
+    let blob = dir.join(record.blob.as_deref().unwrap_or_default());
+    record
+        .content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let extracted = Path::new(line).file_name().map(|name| blob.join(i.to_string()).join(name));
+            match extracted.filter(|path| path.symlink_metadata().is_ok()) {
+                Some(path) => path.display().to_string(),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Agrega un elemento al historial salvo que ya haya uno con el mismo contenido. Si
+// lo hay y sus datos difieren, se conserva el existente, se suma la marca de
+// favorito y se informa el conflicto.
+pub fn merge(record: &Record, data: Option<&[u8]>, report: &mut ImportReport) -> Result<(), Box<dyn Error>> {
+    if !valid_timestamp(&record.created_at) {
+        report.invalid.push(format!("{:?}: bad copy date, expected YYYY-MM-DD HH:MM:SS", record.created_at));
+        return Ok(());
+    }
+    if !matches!(record.content_type.as_str(), "text" | "image" | "files") {
+        report.invalid.push(format!("{}: unknown type {:?}", record.created_at, record.content_type));
+        return Ok(());
+    }
+    if record.content_type == "image" && data.is_none() {
+        report.invalid.push(format!("{}: image without data", record.created_at));
+        return Ok(());
+    }
+    let hash = db::content_hash(&record.content_type, &record.content, data);
+    let Some(existing) = db::find_by_hash(&hash)? else {
+        let id = db::import_clipboard_item(
+            &NewClipboardItem {
+                content_type: &record.content_type,
+                mime: &record.mime,
+                content: &record.content,
+                data,
+                source_app: record.source_app.as_deref(),
+                secret: record.secret,
+            },
+            &record.created_at,
+            record.favorite,
+        )?;
+        for tag in &record.tags {
+            db::tag_item(id, tag)?;
+        }
+        report.imported += 1;
+        return Ok(());
+    };
+    // Las etiquetas se suman sin contarlas como conflicto.
+    for tag in record.tags.iter().filter(|tag| !existing.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag))) {
+        db::tag_item(existing.id, tag)?;
+    }
+    let mut differences = Vec::new();
+    if existing.created_at != record.created_at {
+        differences.push(format!("copied {} here, {} in the import", existing.created_at, record.created_at));
+    }
+    if existing.source_app != record.source_app {
+        differences.push(format!(
+            "source {} here, {} in the import",
+            existing.source_app.as_deref().unwrap_or("unknown"),
+            record.source_app.as_deref().unwrap_or("unknown")
+        ));
+    }
+    if existing.favorite != record.favorite {
+        differences.push("favorite in one of them, kept as favorite".to_string());
+        if record.favorite {
+            db::set_favorite(existing.id, true)?;
+        }
+    }
+    if differences.is_empty() {
+        report.duplicates += 1;
+    } else {
+        let summary = match record.content_type.as_str() {
+            "image" => record.mime.clone(),
+            _ if record.secret => "secret".to_string(),
+            _ => preview::truncate(&preview::normalize(&record.content), 40),
+        };
+        report.conflicts.push(format!("item {} ({summary}): {}", existing.id, differences.join("; ")));
+    }
+    Ok(())
+}
+
+// "2024-01-31 23:59:59", como lo guarda SQLite; otro formato rompería el orden del historial.
+fn valid_timestamp(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let separators = [(4, b'-'), (7, b'-'), (10, b' '), (13, b':'), (16, b':')];
+    if !value.is_ascii() || bytes.len() != 19 || separators.iter().any(|&(i, separator)| bytes[i] != separator) {
+        return false;
+    }
+    let number = |range: std::ops::Range<usize>| {
+        let digits = &value[range];
+        digits.bytes().all(|byte| byte.is_ascii_digit()).then(|| digits.parse::<u32>().ok()).flatten()
+    };
+    let (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) =
+        (number(0..4), number(5..7), number(8..10), number(11..13), number(14..16), number(17..19))
+    else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+    (1..=days).contains(&day) && hour < 24 && minute < 60 && second < 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Cursor;
+
+    fn png() -> Vec<u8> {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(2, 2))
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn timestamps() {
+        assert!(valid_timestamp("2024-02-29 23:59:59"));
+        assert!(!valid_timestamp("2023-02-29 00:00:00"));
+        assert!(!valid_timestamp("2024-01-31T23:59:59"));
+        assert!(!valid_timestamp("2024-13-01 00:00:00"));
+        assert!(!valid_timestamp("yesterday"));
+        assert!(!valid_timestamp("2024-01-01 00:00:0é"));
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = env::temp_dir().join(format!("ropias-backup-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("docs/photos")).unwrap();
+        fs::write(dir.join("docs/report.txt"), "hola").unwrap();
+        fs::write(dir.join("docs/photos/a.txt"), "a").unwrap();
+        let _db = db::use_test_path(dir.join("source/clipboard.db"));
+
+        let text = db::import_clipboard_item(&NewClipboardItem::test_text("select 1"), "2024-01-01 10:00:00", true).unwrap();
+        db::tag_item(text, "sql").unwrap();
+        let png = png();
+        let image = NewClipboardItem {
+            content_type: "image",
+            mime: "image/png",
+            data: Some(&png),
+            ..NewClipboardItem::test_text("")
+        };
+        db::import_clipboard_item(&image, "2024-01-01 10:00:01", false).unwrap();
+        let paths = format!("{}\n{}", dir.join("docs/report.txt").display(), dir.join("docs/photos").display());
+        let files = NewClipboardItem {
+            content_type: "files",
+            mime: "text/uri-list",
+            ..NewClipboardItem::test_text(&paths)
+        };
+        db::import_clipboard_item(&files, "2024-01-01 10:00:02", false).unwrap();
+        let secret = NewClipboardItem {
+            secret: true,
+            ..NewClipboardItem::test_text("hunter2")
+        };
+        db::import_clipboard_item(&secret, "2024-01-01 10:00:03", false).unwrap();
+
+        let archive = dir.join("backup.tar");
+        let exported = export(&archive, Format::Archive, false).unwrap();
+        assert_eq!((exported.exported, exported.skipped_secrets, exported.missing_files), (3, 1, 0));
+        fs::remove_dir_all(dir.join("docs")).unwrap();
+
+        // En la otra máquina ya está el texto, copiado en otra fecha y sin favorito.
+        db::set_path(dir.join("target/clipboard.db"));
+        db::import_clipboard_item(&NewClipboardItem::test_text("select 1"), "2024-02-01 09:00:00", false).unwrap();
+        let imported = import(&archive, Format::Archive).unwrap();
+        assert_eq!((imported.imported, imported.duplicates, imported.conflicts.len()), (2, 0, 1));
+        assert!(imported.invalid.is_empty(), "{:?}", imported.invalid);
+
+        let items = db::list_clipboard_content(None).unwrap();
+        let text = items.iter().find(|item| item.content == "select 1").unwrap();
+        assert!(text.favorite);
+        assert_eq!(text.tags, ["sql"]);
+        let image = items.iter().find(|item| item.content_type == "image").unwrap();
+        assert_eq!(db::get_clipboard_data(image.id).unwrap(), Some(png));
+        let files = items.iter().find(|item| item.content_type == "files").unwrap();
+        let restored: Vec<_> = files.content.lines().map(Path::new).collect();
+        assert!(restored.iter().all(|path| path.starts_with(dir.join("target/files"))));
+        assert_eq!(fs::read_to_string(restored[0]).unwrap(), "hola");
+        assert_eq!(fs::read_to_string(restored[1].join("a.txt")).unwrap(), "a");
+
+        let again = import(&archive, Format::Archive).unwrap();
+        assert_eq!((again.imported, again.duplicates, again.conflicts.len()), (0, 2, 1));
+        assert_eq!(db::list_clipboard_content(None).unwrap().len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+use arboard::Clipboard;
+
+use crate::actions::{self, Outcome};
+use crate::db::{self, ClearScope, ClipboardItem, ChangeWatcher, PageQuery, Snippet, Tag, TrashedItem, APP_NAME};
+use crate::ipc::{Client, Event, Request, Response, Status};
+use crate::server::{not_in_trash, write_clipboard};
+use crate::snippets::{Template, Values};
+use crate::transform::Pipeline;
+
+// Operaciones sobre el historial para la CLI y la GUI: se usa el daemon si está
+// corriendo y, si no, se accede directamente a la base de datos.
+
+fn daemon(request: Request) -> Option<Result<Response, Box<dyn Error>>> {
+    Client::connect().map(|mut client| client.request(request))
+}
+
+fn unexpected(response: Response) -> Box<dyn Error> {
+    format!("unexpected response from daemon: {response:?}").into()
+}
+
+pub fn list(limit: Option<usize>) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    match daemon(Request::List { limit }) {
+        Some(Ok(Response::Items { items })) => Ok(items),
+        _ => db::list_clipboard_content(limit),
+    }
+}
+
+pub fn search(query: &str, limit: Option<usize>) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    let request = Request::Search {
+        query: query.to_string(),
+        limit,
+    };
+    match daemon(request) {
+        Some(Ok(Response::Items { items })) => Ok(items),
+        _ => db::search_clipboard_content(query, limit),
+    }
+}
+
+pub fn page(query: &PageQuery) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    match daemon(Request::Page { query: query.clone() }) {
+        Some(Ok(Response::Items { items })) => Ok(items),
+        _ => db::page_clipboard_content(query),
+    }
+}
+
+pub fn get(id: i32) -> Result<Option<ClipboardItem>, Box<dyn Error>> {
+    match daemon(Request::Get { id }) {
+        Some(Ok(Response::Item { item })) => Ok(item),
+        _ => db::get_clipboard_item(id),
+    }
+}
+
+pub fn delete(id: i32) -> Result<(), Box<dyn Error>> {
+    match daemon(Request::Delete { id }) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => match db::delete_clipboard_item(id)? {
+            true => Ok(()),
+            false => Err(format!("clipboard item {id} not found").into()),
+        },
+    }
+}
+
+pub fn pin(id: i32, pinned: bool) -> Result<(), Box<dyn Error>> {
+    match daemon(Request::Pin { id, pinned }) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => match db::set_favorite(id, pinned)? {
+            true => Ok(()),
+            false => Err(format!("clipboard item {id} not found").into()),
+        },
+    }
+}
+
+pub fn edit(id: i32, content: &str) -> Result<(), Box<dyn Error>> {
+    let request = Request::Edit {
+        id,
+        content: content.to_string(),
+    };
+    match daemon(request) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => match db::update_clipboard_content(id, content)? {
+            true => Ok(()),
+            false => Err(format!("clipboard item {id} not found or not editable").into()),
+        },
+    }
+}
+
+// Agrega o quita una etiqueta.
+pub fn tag(id: i32, tag: &str, tagged: bool) -> Result<(), Box<dyn Error>> {
+    let request = match tagged {
+        true => Request::Tag { id, tag: tag.to_string() },
+        false => Request::Untag { id, tag: tag.to_string() },
+    };
+    match daemon(request) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None if tagged => match db::tag_item(id, tag)? {
+            true => Ok(()),
+            false => Err(format!("clipboard item {id} not found").into()),
+        },
+        None => match db::untag_item(id, tag)? {
+            true => Ok(()),
+            false => Err(format!("clipboard item {id} is not tagged {tag:?}").into()),
+        },
+    }
+}
+
+pub fn tags() -> Result<Vec<Tag>, Box<dyn Error>> {
+    match daemon(Request::Tags) {
+        Some(Ok(Response::Tags { tags })) => Ok(tags),
+        _ => db::list_tags(),
+    }
+}
+
+// Manda a la papelera lo que abarca `scope` y devuelve los ids, para poder
+// deshacerlo con `restore`. Con `secure` se borra para siempre.
+pub fn clear(scope: &ClearScope, secure: bool) -> Result<Vec<i32>, Box<dyn Error>> {
+    let request = Request::Clear {
+        scope: scope.clone(),
+        secure,
+    };
+    match daemon(request) {
+        Some(Ok(Response::Ids { ids })) => Ok(ids),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None if secure => db::wipe_clipboard_content(scope),
+        None => db::clear_clipboard_content(scope),
+    }
+}
+
+pub fn trash() -> Result<Vec<TrashedItem>, Box<dyn Error>> {
+    match daemon(Request::Trash) {
+        Some(Ok(Response::Trash { items })) => Ok(items),
+        _ => db::list_trash(),
+    }
+}
+
+// Saca elementos de la papelera y devuelve los que estaban en ella.
+pub fn restore(ids: &[i32]) -> Result<Vec<i32>, Box<dyn Error>> {
+    match daemon(Request::Restore { ids: ids.to_vec() }) {
+        Some(Ok(Response::Ids { ids })) => Ok(ids),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => match db::restore_clipboard_items(ids)? {
+            restored if restored.is_empty() && !ids.is_empty() => Err(not_in_trash(ids)),
+            restored => Ok(restored),
+        },
+    }
+}
+
+pub fn empty_trash() -> Result<(), Box<dyn Error>> {
+    match daemon(Request::EmptyTrash) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => db::empty_trash(None).map(|_| ()),
+    }
+}
+
+// Sin daemon el contenido solo vive mientras el proceso que lo copió siga abierto.
+pub fn set_clipboard(id: i32, plain: bool) -> Result<(), Box<dyn Error>> {
+    match daemon(Request::SetClipboard { id, plain }) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => {
+            let item = db::get_clipboard_item(id)?
+                .ok_or_else(|| format!("clipboard item {id} not found"))?;
+            write_clipboard(&item, plain)?;
+            Ok(())
+        }
+    }
+}
+
+// Guarda un texto nuevo en el historial y lo copia.
+pub fn copy_text(content: &str) -> Result<ClipboardItem, Box<dyn Error>> {
+    let request = Request::CopyText {
+        content: content.to_string(),
+    };
+    match daemon(request) {
+        Some(Ok(Response::Item { item: Some(item) })) => Ok(item),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => {
+            let id = db::save_clipboard_content(content, Some(APP_NAME))?;
+            Clipboard::new()?.set_text(content)?;
+            db::get_clipboard_item(id)?.ok_or_else(|| format!("clipboard item {id} not found").into())
+        }
+    }
+}
+
+// Ejecuta una acción sobre un elemento; si produce un texto, se copia como elemento nuevo.
+pub fn action(id: i32, name: &str) -> Result<Outcome, Box<dyn Error>> {
+    let action = actions::find(name).ok_or_else(|| format!("unknown action {name:?}"))?;
+    let item = get(id)?.ok_or_else(|| format!("clipboard item {id} not found"))?;
+    let outcome = action.run(&item)?;
+    if let Outcome::Text(content) = &outcome {
+        copy_text(content)?;
+    }
+    Ok(outcome)
+}
+
+// Copia el texto de un elemento después de transformarlo, como elemento nuevo.
+pub fn copy_transformed(id: i32, pipeline: &Pipeline) -> Result<ClipboardItem, Box<dyn Error>> {
+    let item = get(id)?.ok_or_else(|| format!("clipboard item {id} not found"))?;
+    if item.content_type == "image" || item.secret {
+        return Err(format!("clipboard item {id} cannot be transformed").into());
+    }
+    copy_text(&pipeline.apply(&item.content))
+}
+
+// Completa los marcadores de un snippet. `inputs` tiene las respuestas a sus `{{input:...}}`.
+pub fn expand_snippet(snippet: &Snippet, inputs: HashMap<String, String>) -> Result<String, Box<dyn Error>> {
+    let template = Template::parse(&snippet.body)?;
+    let clipboard = match template.uses_clipboard() {
+        true => clipboard_text()?,
+        false => String::new(),
+    };
+    Ok(template.expand(&Values {
+        now: db::local_time()?,
+        clipboard,
+        inputs,
+    }))
+}
+
+// Lo que hay en el portapapeles o, si no se puede leer, el último texto del historial.
+fn clipboard_text() -> Result<String, Box<dyn Error>> {
+    if let Ok(text) = Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        return Ok(text);
+    }
+    let latest = page(&PageQuery {
+        content_type: Some("text".to_string()),
+        limit: Some(1),
+        ..PageQuery::default()
+    })?;
+    Ok(latest.into_iter().next().map(|item| item.content).unwrap_or_default())
+}
+
+// Las siguientes operaciones solo tienen sentido con el daemon corriendo.
+fn require_daemon(request: Request) -> Result<(), Box<dyn Error>> {
+    match daemon(request) {
+        Some(Ok(Response::Done)) => Ok(()),
+        Some(Ok(response)) => Err(unexpected(response)),
+        Some(Err(err)) => Err(err),
+        None => Err("the daemon is not running".into()),
+    }
+}
+
+pub fn status() -> Option<Status> {
+    match daemon(Request::Status)? {
+        Ok(Response::Status { status }) => Some(status),
+        _ => None,
+    }
+}
+
+pub fn pause(minutes: Option<u64>) -> Result<(), Box<dyn Error>> {
+    require_daemon(Request::Pause { minutes })
+}
+
+pub fn resume() -> Result<(), Box<dyn Error>> {
+    require_daemon(Request::Resume)
+}
+
+pub fn set_incognito(enabled: bool) -> Result<(), Box<dyn Error>> {
+    require_daemon(Request::Incognito { enabled })
+}
+
+// Un cambio en el historial hecho por otro proceso.
+#[derive(Debug, Clone)]
+pub enum HistoryChange {
+    Event(Event),
+    // Sin detalle de qué cambió: hay que volver a leer el historial.
+    Reload,
+}
+
+// Sin daemon se revisa la base de datos y si el daemon arrancó con esta frecuencia.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Bloquea hasta que `notify` devuelve false. Con el daemon corriendo se reciben sus
+// eventos; sin él, se detectan los cambios en la base de datos.
+pub fn watch(mut notify: impl FnMut(HistoryChange) -> bool) {
+    let mut first = true;
+    loop {
+        if let Some(mut client) = Client::connect() {
+            if client.request(Request::Subscribe).is_ok() {
+                // Lo que cambió mientras no había suscripción.
+                if !first && !notify(HistoryChange::Reload) {
+                    return;
+                }
+                while let Ok(Some(event)) = client.next_event() {
+                    if !notify(HistoryChange::Event(event)) {
+                        return;
+                    }
+                }
+                // El daemon se detuvo.
+                if !notify(HistoryChange::Reload) {
+                    return;
+                }
+            }
+        }
+        first = false;
+
+        let mut watcher = ChangeWatcher::new().ok();
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let changed = watcher
+                .as_mut()
+                .is_some_and(|watcher| watcher.changed().unwrap_or_default());
+            if changed && !notify(HistoryChange::Reload) {
+                return;
+            }
+            if Client::connect().is_some() {
+                break;
+            }
+        }
+    }
+}
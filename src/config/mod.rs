@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use directories_next::ProjectDirs;
+use serde::Deserialize;
+
+use crate::rules::Rules;
+use crate::secrets::{SecretAction, SecretDetector};
+use crate::transform::{Pipeline, Transform};
+
+// Configuración de `$XDG_CONFIG_HOME/ropias/config.toml`. Todas las secciones son
+// opcionales y las claves desconocidas se rechazan para detectar errores de escritura.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub capture: CaptureConfig,
+    pub retention: RetentionConfig,
+    pub trash: TrashConfig,
+    pub rules: Rules,
+    pub secrets: SecretsConfig,
+    pub gui: GuiConfig,
+    pub keybindings: BTreeMap<String, KeyBinding>,
+    pub theme: Option<String>,
+    // Presets de transformaciones: nombre → pasos, por ejemplo `slug = ["trim", "snake"]`.
+    pub transforms: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub path: Option<PathBuf>,
+}
+
+// Tipos de contenido que guarda el daemon.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    pub text: bool,
+    pub images: bool,
+    pub files: bool,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            text: true,
+            images: true,
+            files: true,
+        }
+    }
+}
+
+// Límites del historial. Los favoritos y lo etiquetado nunca se eliminan, salvo
+// lo que tiene alguna de las etiquetas de `expire_tags` y ninguna otra.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub max_items: Option<usize>,
+    pub max_age_days: Option<u32>,
+    pub expire_tags: Vec<String>,
+}
+
+// Lo borrado pasa a la papelera y se purga tras `purge_after_days` días; 0 lo
+// deja hasta vaciarla a mano.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashConfig {
+    pub purge_after_days: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig { purge_after_days: 30 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig {
+    pub action: SecretAction,
+    // Patrones adicionales que se consideran secretos.
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuiConfig {
+    // Dónde aparece `ropias popup`.
+    pub popup_position: PopupPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PopupPosition {
+    #[default]
+    Center,
+    // Solo en X11; en Wayland se usa el centro.
+    Cursor,
+}
+
+// Un atajo puede tener una combinación ("ctrl+n") o varias (["down", "ctrl+n"]).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    fn strings(&self) -> &[String] {
+        match self {
+            KeyBinding::One(combo) => std::slice::from_ref(combo),
+            KeyBinding::Many(combos) => combos,
+        }
+    }
+
+    pub fn combos(&self) -> Vec<KeyCombo> {
+        self.strings()
+            .iter()
+            .filter_map(|combo| KeyCombo::parse(combo).ok())
+            .collect()
+    }
+}
+
+// Acciones de la interfaz que se pueden asignar a un atajo, con sus teclas por defecto.
+pub const KEY_ACTIONS: &[(&str, &[&str])] = &[
+    ("focus-next", &["tab"]),
+    ("focus-previous", &["shift+tab"]),
+    ("select-next", &["down", "ctrl+n"]),
+    ("select-previous", &["up", "ctrl+p"]),
+    ("copy", &["enter"]),
+    ("paste-plain", &["shift+enter"]),
+    ("delete", &["ctrl+d"]),
+    ("pin", &["ctrl+s"]),
+    ("slot-1", &["alt+1"]),
+    ("slot-2", &["alt+2"]),
+    ("slot-3", &["alt+3"]),
+    ("slot-4", &["alt+4"]),
+    ("slot-5", &["alt+5"]),
+    ("slot-6", &["alt+6"]),
+    ("slot-7", &["alt+7"]),
+    ("slot-8", &["alt+8"]),
+    ("slot-9", &["alt+9"]),
+    ("toggle-preview", &["f3"]),
+    ("undo", &["ctrl+z"]),
+    ("close", &["escape"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+    // Nombre de la tecla en minúsculas: "a", "1", "enter", "up", "f5"...
+    pub key: String,
+}
+
+impl KeyCombo {
+    pub fn parse(combo: &str) -> Result<Self, String> {
+        let mut parsed = KeyCombo {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+            key: String::new(),
+        };
+        let parts: Vec<String> = combo.split('+').map(|part| part.trim().to_lowercase()).collect();
+        let (key, modifiers) = parts.split_last().ok_or("empty key binding")?;
+        for modifier in modifiers {
+            match modifier.as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "alt" => parsed.alt = true,
+                "shift" => parsed.shift = true,
+                "super" | "logo" | "meta" => parsed.logo = true,
+                _ => return Err(format!("unknown modifier {modifier:?} in {combo:?}")),
+            }
+        }
+        if key.is_empty() {
+            return Err(format!("missing key in {combo:?}"));
+        }
+        parsed.key = key.clone();
+        Ok(parsed)
+    }
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "ropias")
+}
+
+// `ROPIAS_CONFIG` permite usar otro archivo de configuración.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("ROPIAS_CONFIG") {
+        return PathBuf::from(path);
+    }
+    project_dirs()
+        .map(|dirs| dirs.config_dir().join("config.toml"))
+        .unwrap_or_else(|| PathBuf::from("config.toml"))
+}
+
+impl Config {
+    // Sin archivo de configuración se usan los valores por defecto.
+    pub fn load(path: &Path) -> Result<Config, Box<dyn Error>> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(format!("cannot read {}: {err}", path.display()).into()),
+        };
+        let config: Config = toml::from_str(&source)
+            .map_err(|err| format!("invalid configuration in {}:\n{err}", path.display()))?;
+        config
+            .validate()
+            .map_err(|errors| format!("invalid configuration in {}:\n  {}", path.display(), errors.join("\n  ")))?;
+        Ok(config)
+    }
+
+    // Comprueba lo que el esquema de TOML no puede expresar.
+    fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.retention.max_items == Some(0) {
+            errors.push("retention.max_items must be greater than 0".to_string());
+        }
+        if self.retention.max_age_days == Some(0) {
+            errors.push("retention.max_age_days must be greater than 0".to_string());
+        }
+        let content = &self.rules.content;
+        if let (Some(min), Some(max)) = (content.min_size, content.max_size) {
+            if min > max {
+                errors.push(format!(
+                    "rules.content.min_size ({min}) is greater than rules.content.max_size ({max})"
+                ));
+            }
+        }
+        if let Err(err) = self.rules.compile() {
+            errors.push(format!("rules.content.patterns: {err}"));
+        }
+        if let Err(err) = SecretDetector::new(&self.secrets.patterns) {
+            errors.push(format!("secrets.patterns: {err}"));
+        }
+        for (action, binding) in &self.keybindings {
+            if !KEY_ACTIONS.iter().any(|(name, _)| name == action) {
+                errors.push(format!(
+                    "keybindings.{action}: unknown action, expected one of {}",
+                    KEY_ACTIONS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+                ));
+            }
+            for combo in binding.strings() {
+                if let Err(err) = KeyCombo::parse(combo) {
+                    errors.push(format!("keybindings.{action}: {err}"));
+                }
+            }
+        }
+        for (preset, steps) in &self.transforms {
+            if Transform::parse(preset).is_some() {
+                errors.push(format!("transforms.{preset}: the name of a preset cannot be a transform"));
+            }
+            if let Err(err) = Pipeline::parse(&steps.join(","), &BTreeMap::new()) {
+                errors.push(format!("transforms.{preset}: {err}"));
+            }
+        }
+        if let Some(theme) = &self.theme {
+            if !iced::Theme::ALL.iter().any(|known| known.to_string() == *theme) {
+                errors.push(format!(
+                    "theme: unknown theme {theme:?}, expected one of {}",
+                    iced::Theme::ALL.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        self.database
+            .path
+            .clone()
+            .or_else(|| project_dirs().map(|dirs| dirs.data_dir().join("clipboard.db")))
+            .unwrap_or_else(|| PathBuf::from("clipboard.db"))
+    }
+
+    // Atajos de una acción: los configurados o, si no hay, los de por defecto.
+    pub fn key_combos(&self, action: &str) -> Vec<KeyCombo> {
+        match self.keybindings.get(action) {
+            Some(binding) => binding.combos(),
+            None => KEY_ACTIONS
+                .iter()
+                .find(|(name, _)| *name == action)
+                .map(|(_, combos)| combos.iter().filter_map(|combo| KeyCombo::parse(combo).ok()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn theme(&self) -> iced::Theme {
+        self.theme
+            .as_ref()
+            .and_then(|theme| iced::Theme::ALL.iter().find(|known| known.to_string() == *theme))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+// Detecta cambios en el archivo de configuración comparando su fecha de modificación.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        ConfigWatcher { path, modified }
+    }
+
+    // Devuelve la configuración nueva si el archivo cambió desde la última llamada.
+    pub fn poll(&mut self) -> Option<Result<Config, Box<dyn Error>>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path))
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Config {
+        toml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn key_combos() {
+        let combo = KeyCombo::parse("Ctrl+Shift+Enter").unwrap();
+        assert!(combo.ctrl && combo.shift && !combo.alt && !combo.logo);
+        assert_eq!(combo.key, "enter");
+        assert_eq!(KeyCombo::parse("super + j").unwrap().key, "j");
+        assert!(KeyCombo::parse("hyper+j").is_err());
+        assert!(KeyCombo::parse("ctrl+").is_err());
+    }
+
+    #[test]
+    fn valid_config() {
+        let config = parse(
+            r#"
+            [retention]
+            max_items = 10
+            [keybindings]
+            select-next = ["down", "ctrl+j"]
+            [transforms]
+            slug = ["trim", "snake"]
+            "#,
+        );
+        assert!(config.validate().is_ok());
+        assert_eq!(config.key_combos("select-next")[1].key, "j");
+        assert_eq!(config.key_combos("copy"), [KeyCombo::parse("enter").unwrap()]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = parse(
+            r#"
+            theme = "Nope"
+            [retention]
+            max_items = 0
+            [rules.content]
+            min_size = 10
+            max_size = 5
+            [secrets]
+            patterns = ["("]
+            [keybindings]
+            jump = "ctrl+j"
+            copy = "hyper+c"
+            [transforms]
+            trim = ["lower"]
+            "#,
+        );
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 7, "{errors:#?}");
+        assert!(errors.iter().any(|err| err.starts_with("keybindings.jump: unknown action")));
+        assert!(toml::from_str::<Config>("[retention]\nmax_itens = 1").is_err());
+    }
+}
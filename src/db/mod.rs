@@ -1,13 +1,40 @@
+use std::env;
 use std::error::Error;
-use rusqlite::{Connection, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex, RwLock};
+use regex::Regex;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::kind::{self, Kind};
+
+const DB_PATH: &str = "clipboard.db";
+
+// Aplicación de origen de lo que guarda ropias por su cuenta, como el resultado de una acción.
+pub const APP_NAME: &str = "ropias";
+
+// Ruta configurada en `database.path`; puede cambiar si se recarga la configuración.
+static CONFIGURED_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+pub fn set_path(path: PathBuf) {
+    *CONFIGURED_PATH.write().unwrap() = Some(path);
+}
 
 pub struct DbConfig {
     pub path: String,
     pub encrypt: bool,
 }
 
-pub fn init_db(config: &DbConfig) -> Result<(), Box<dyn Error>> {
+pub fn init_db(config: &DbConfig) -> Result<Connection, Box<dyn Error>> {
+    if let Some(dir) = PathBuf::from(&config.path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
     let conn = Connection::open(&config.path)?;
+    // Para que al borrar un elemento se borren también sus etiquetas.
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
     conn.execute(
         "CREATE TABLE IF NOT EXISTS clipboard (
             id INTEGER PRIMARY KEY,
@@ -17,46 +44,926 @@ pub fn init_db(config: &DbConfig) -> Result<(), Box<dyn Error>> {
         )",
         [],
     )?;
+    add_column(&conn, "favorite", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(&conn, "source_app", "TEXT")?;
+    add_column(&conn, "mime", "TEXT NOT NULL DEFAULT 'text/plain'")?;
+    // Contenido binario, por ejemplo el PNG de una imagen.
+    add_column(&conn, "data", "BLOB")?;
+    // Contenido que parece una contraseña o un token y se oculta en las listas.
+    add_column(&conn, "secret", "INTEGER NOT NULL DEFAULT 0")?;
+    // Qué parece ser un texto (enlace, correo, código...) y en qué lenguaje está el código.
+    let added_kind = add_column(&conn, "kind", "TEXT")?;
+    add_column(&conn, "language", "TEXT")?;
+    if added_kind {
+        classify_existing(&conn)?;
+    }
+    // Huella del contenido, para no duplicar elementos al importar.
+    if add_column(&conn, "hash", "TEXT")? {
+        hash_existing(&conn)?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS clipboard_hash ON clipboard (hash)", [])?;
+    // Los elementos borrados quedan en la papelera hasta que se vacía o se purgan.
+    add_column(&conn, "deleted_at", "TEXT")?;
+    // Para paginar el historial en orden sin recorrer toda la tabla.
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS clipboard_created_at ON clipboard (created_at, id)",
+        [],
+    )?;
+    // Etiquetas para agrupar elementos en colecciones como "sql" o "kubectl".
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+        CREATE TABLE IF NOT EXISTS clipboard_tags (
+            clipboard_id INTEGER NOT NULL REFERENCES clipboard (id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+            PRIMARY KEY (clipboard_id, tag_id)
+        )",
+    )?;
+    // Textos guardados a mano, con marcadores que se completan al copiarlos.
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS snippets (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )?;
+    Ok(conn)
+}
+
+// Agrega columnas nuevas a bases de datos creadas con versiones anteriores.
+// Devuelve si la columna se acaba de agregar.
+fn add_column(conn: &Connection, column: &str, definition: &str) -> Result<bool, Box<dyn Error>> {
+    let exists = conn
+        .prepare("SELECT 1 FROM pragma_table_info('clipboard') WHERE name = ?1")?
+        .exists([column])?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE clipboard ADD COLUMN {column} {definition}"), [])?;
+    }
+    Ok(!exists)
+}
+
+// Clasifica los textos guardados antes de que existiera la columna `kind`.
+fn classify_existing(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT id, content FROM clipboard WHERE type = 'text' AND secret = 0")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    let mut update = conn.prepare("UPDATE clipboard SET kind = ?2, language = ?3 WHERE id = ?1")?;
+    for (id, content) in rows {
+        let classification = kind::classify(&content);
+        update.execute(params![id, classification.kind.as_str(), classification.language])?;
+    }
+    Ok(())
+}
+
+fn hash_existing(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare("SELECT id, type, content, data FROM clipboard")?;
+    let rows = stmt
+        .query_map([], |row| {
+            let hash = content_hash(&row.get::<_, String>(1)?, &row.get::<_, String>(2)?, row.get::<_, Option<Vec<u8>>>(3)?.as_deref());
+            Ok((row.get::<_, i32>(0)?, hash))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    let mut update = conn.prepare("UPDATE clipboard SET hash = ?2 WHERE id = ?1")?;
+    for (id, hash) in rows {
+        update.execute(params![id, hash])?;
+    }
     Ok(())
 }
 
-pub fn save_clipboard_content(content: &String) -> Result<(), Box<dyn Error>> {
-    let conn = Connection::open("clipboard.db")?;
-    let db_config = DbConfig {
-        path: "clipboard.db".to_string(),
+// SHA-256 del tipo y el contenido, en hexadecimal.
+pub fn content_hash(content_type: &str, content: &str, data: Option<&[u8]>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content_type.as_bytes());
+    hasher.update([0]);
+    hasher.update(content.as_bytes());
+    hasher.update([0]);
+    hasher.update(data.unwrap_or_default());
+    hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Solo se clasifican los textos que se muestran.
+fn classification(content_type: &str, content: &str, secret: bool) -> (Option<&'static str>, Option<&'static str>) {
+    if content_type != "text" || secret {
+        return (None, None);
+    }
+    let classification = kind::classify(content);
+    (Some(classification.kind.as_str()), classification.language)
+}
+
+// `ROPIAS_DB` tiene prioridad sobre la configuración, por ejemplo en pruebas.
+fn path() -> String {
+    env::var("ROPIAS_DB").unwrap_or_else(|_| {
+        CONFIGURED_PATH
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or_else(|| DB_PATH.to_string(), |path| path.to_string_lossy().into_owned())
+    })
+}
+
+// Las migraciones de `init_db` corren una vez por base de datos; después solo se
+// abre la conexión. Sin `SQLITE_OPEN_CREATE`, si el archivo se borró se vuelve a crear.
+static MIGRATED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Mutex::default);
+
+pub fn connect() -> Result<Connection, Box<dyn Error>> {
+    let path = path();
+    let mut migrated = MIGRATED.lock().unwrap();
+    if migrated.contains(&path) {
+        let flags = OpenFlags::default() - OpenFlags::SQLITE_OPEN_CREATE;
+        if let Ok(conn) = Connection::open_with_flags(&path, flags) {
+            conn.execute_batch("PRAGMA foreign_keys = ON")?;
+            return Ok(conn);
+        }
+    }
+    let conn = init_db(&DbConfig {
+        path: path.clone(),
         encrypt: false,
-    };
-    init_db(&db_config)?;
+    })?;
+    migrated.insert(path);
+    Ok(conn)
+}
+
+// Carpeta de la base de datos. Ahí también quedan los archivos importados de una
+// copia de seguridad, ya que sus rutas originales son de otra máquina.
+pub fn data_dir() -> PathBuf {
+    let path = PathBuf::from(path());
+    path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+}
+
+// La ruta de la base de datos es global: las pruebas que la usan se turnan.
+#[cfg(test)]
+pub fn use_test_path(path: PathBuf) -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let guard = LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    set_path(path);
+    guard
+}
+
+// Contenido recién copiado, antes de guardarse.
+#[derive(Debug, Clone)]
+pub struct NewClipboardItem<'a> {
+    // "text", "image" o "files".
+    pub content_type: &'a str,
+    pub mime: &'a str,
+    pub content: &'a str,
+    pub data: Option<&'a [u8]>,
+    pub source_app: Option<&'a str>,
+    pub secret: bool,
+}
+
+impl<'a> NewClipboardItem<'a> {
+    // Tamaño en bytes de lo copiado.
+    pub fn size(&self) -> usize {
+        self.data.map_or(self.content.len(), <[u8]>::len)
+    }
+
+    // Un texto recién copiado, para las pruebas.
+    #[cfg(test)]
+    pub fn test_text(content: &'a str) -> Self {
+        NewClipboardItem {
+            content_type: "text",
+            mime: "text/plain",
+            content,
+            data: None,
+            source_app: None,
+            secret: false,
+        }
+    }
+}
+
+pub fn save_clipboard_item(item: &NewClipboardItem) -> Result<i32, Box<dyn Error>> {
+    insert_clipboard_item(item, None, false)
+}
+
+// Guarda un elemento importado con su fecha y su marca de favorito originales.
+pub fn import_clipboard_item(item: &NewClipboardItem, created_at: &str, favorite: bool) -> Result<i32, Box<dyn Error>> {
+    insert_clipboard_item(item, Some(created_at), favorite)
+}
+
+fn insert_clipboard_item(item: &NewClipboardItem, created_at: Option<&str>, favorite: bool) -> Result<i32, Box<dyn Error>> {
+    let conn = connect()?;
+    let (kind, language) = classification(item.content_type, item.content, item.secret);
     conn.execute(
-        "INSERT INTO clipboard (content, created_at) VALUES (?1, datetime('now'))",
-        &[content],
+        "INSERT INTO clipboard (content, type, mime, data, source_app, secret, kind, language, hash, favorite, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, COALESCE(?11, datetime('now')))",
+        params![
+            item.content,
+            item.content_type,
+            item.mime,
+            item.data,
+            item.source_app,
+            item.secret,
+            kind,
+            language,
+            content_hash(item.content_type, item.content, item.data),
+            favorite,
+            created_at
+        ],
     )?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+// El elemento más antiguo con el mismo contenido, si lo hay.
+pub fn find_by_hash(hash: &str) -> Result<Option<ClipboardItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let item = conn
+        .query_row(
+            &format!("SELECT {ITEM_COLUMNS} FROM clipboard WHERE hash = ?1 AND deleted_at IS NULL ORDER BY created_at, id LIMIT 1"),
+            [hash],
+            ClipboardItem::from_row,
+        )
+        .optional()?;
+    Ok(item)
+}
+
+// Recorre todo el historial del más antiguo al más reciente, con el contenido binario.
+// Lo que está en la papelera no se incluye.
+pub fn for_each_clipboard_item(
+    mut f: impl FnMut(ClipboardItem, Option<Vec<u8>>) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let conn = connect()?;
+    let mut stmt = conn.prepare(&format!("SELECT {ITEM_COLUMNS}, data FROM clipboard WHERE deleted_at IS NULL ORDER BY created_at, id"))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        f(ClipboardItem::from_row(row)?, row.get(11)?)?;
+    }
     Ok(())
 }
 
+pub fn save_clipboard_content(content: &str, source_app: Option<&str>) -> Result<i32, Box<dyn Error>> {
+    save_clipboard_item(&NewClipboardItem {
+        content_type: "text",
+        mime: "text/plain",
+        content,
+        data: None,
+        source_app,
+        secret: false,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipboardItem {
     pub id: i32,
     pub content: String,
+    #[serde(rename = "type", default = "default_content_type")]
+    pub content_type: String,
+    #[serde(default)]
+    pub mime: String,
     pub created_at: String,
     pub favorite: bool,
+    // Aplicación que copió el contenido, si se pudo identificar.
+    #[serde(default)]
+    pub source_app: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
+    // Solo en textos que no son secretos.
+    #[serde(default)]
+    pub kind: Option<Kind>,
+    #[serde(default)]
+    pub language: Option<String>,
+    // En orden alfabético.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_content_type() -> String {
+    "text".to_string()
+}
+
+const ITEM_COLUMNS: &str = "id, content, type, mime, created_at, favorite, source_app, secret, kind, language,
+    (SELECT group_concat(tags.name, ',') FROM clipboard_tags JOIN tags ON tags.id = clipboard_tags.tag_id
+     WHERE clipboard_tags.clipboard_id = clipboard.id)";
+
+impl ClipboardItem {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(ClipboardItem {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            content_type: row.get(2)?,
+            mime: row.get(3)?,
+            created_at: row.get(4)?,
+            favorite: row.get(5)?,
+            source_app: row.get(6)?,
+            secret: row.get(7)?,
+            kind: row.get::<_, Option<String>>(8)?.as_deref().and_then(Kind::parse),
+            language: row.get(9)?,
+            tags: {
+                let tags: Option<String> = row.get(10)?;
+                let mut tags: Vec<String> = tags.iter().flat_map(|tags| tags.split(',')).map(String::from).collect();
+                tags.sort_by_key(|tag| tag.to_lowercase());
+                tags
+            },
+        })
+    }
+
+    // Un texto guardado y clasificado como lo haría `save_clipboard_item`, para las pruebas.
+    #[cfg(test)]
+    pub fn test_text(content: &str) -> Self {
+        let (kind, language) = classification("text", content, false);
+        ClipboardItem {
+            id: 1,
+            content: content.to_string(),
+            content_type: "text".to_string(),
+            mime: "text/plain".to_string(),
+            created_at: "2024-01-01 00:00:00".to_string(),
+            favorite: false,
+            source_app: None,
+            secret: false,
+            kind: kind.and_then(Kind::parse),
+            language: language.map(String::from),
+            tags: Vec::new(),
+        }
+    }
+}
+
+pub fn list_clipboard_content(limit: Option<usize>) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ITEM_COLUMNS} FROM clipboard WHERE deleted_at IS NULL ORDER BY created_at DESC, id DESC LIMIT ?1"
+    ))?;
+    let rows = stmt.query_map([sql_limit(limit)], ClipboardItem::from_row)?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+pub fn search_clipboard_content(query: &str, limit: Option<usize>) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let terms = SearchTerms::parse(query);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ITEM_COLUMNS} FROM clipboard
+         WHERE deleted_at IS NULL AND (?1 = '' OR (secret = 0 AND content LIKE ?2 ESCAPE '\\')) AND {}
+         ORDER BY created_at DESC, id DESC LIMIT ?3",
+        has_tags(4)
+    ))?;
+    let rows = stmt.query_map(
+        params![terms.text, like_pattern(&terms.text), sql_limit(limit), serde_json::to_string(&terms.tags)?],
+        ClipboardItem::from_row,
+    )?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+// Una búsqueda puede llevar `tag:sql` o `tag:"customer emails"`: se muestran los
+// elementos que tienen todas esas etiquetas y el resto se busca en el contenido.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchTerms {
+    pub text: String,
+    pub tags: Vec<String>,
+}
+
+static TAG_TERM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?:^|\s)tag:(?:"([^"]*)"|(\S+))"#).unwrap());
+
+impl SearchTerms {
+    pub fn parse(query: &str) -> SearchTerms {
+        let tags: Vec<String> = TAG_TERM
+            .captures_iter(query)
+            .filter_map(|captures| captures.get(1).or(captures.get(2)))
+            .map(|tag| tag.as_str().trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        // Sin etiquetas la búsqueda queda tal cual, espacios incluidos.
+        let text = match tags.is_empty() {
+            true => query.to_string(),
+            false => TAG_TERM.replace_all(query, " ").split_whitespace().collect::<Vec<_>>().join(" "),
+        };
+        SearchTerms { text, tags }
+    }
+}
+
+// Condición de que el elemento tenga todas las etiquetas del arreglo JSON en el
+// parámetro `param`.
+fn has_tags(param: usize) -> String {
+    format!(
+        "NOT EXISTS (
+            SELECT 1 FROM json_each(?{param}) AS wanted WHERE NOT EXISTS (
+                SELECT 1 FROM clipboard_tags JOIN tags ON tags.id = clipboard_tags.tag_id
+                WHERE clipboard_tags.clipboard_id = clipboard.id AND tags.name = wanted.value
+            )
+        )"
+    )
+}
+
+fn like_pattern(query: &str) -> String {
+    format!(
+        "%{}%",
+        query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    )
+}
+
+// Posición del último elemento de una página, para pedir la siguiente.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub created_at: String,
+    pub id: i32,
+}
+
+impl From<&ClipboardItem> for PageCursor {
+    fn from(item: &ClipboardItem) -> Self {
+        PageCursor {
+            created_at: item.created_at.clone(),
+            id: item.id,
+        }
+    }
+}
+
+// Una página del historial, del más reciente al más antiguo. La búsqueda no mira
+// el contenido de los secretos pero sí la aplicación de origen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub search: String,
+    #[serde(default)]
+    pub favorites: bool,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub kind: Option<Kind>,
+    // Además de las etiquetas de `tag:` en la búsqueda.
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub after: Option<PageCursor>,
+    // Sin límite con `None`.
+    #[serde(default)]
+    pub limit: Option<usize>,
 }
 
-pub fn get_clipboard_content() -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
-    let conn = Connection::open("clipboard.db")?;
-    let mut stmt = conn.prepare("SELECT * FROM clipboard ORDER BY created_at DESC")?;
-    let rows = stmt.query_map([], |row|
-        Ok(
-            ClipboardItem {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                created_at: row.get(2)?,
-                favorite: false, // TODO: Implementar favoritos
-            }
-        ),
-    )?;
-    let mut items = Vec::new();
-    for item in rows {
-        items.push(item?);
-    }
-    Ok(items)
+// Sin cursor se compara con una fecha mayor que cualquiera, para que SQLite
+// siempre pueda buscar el comienzo de la página en el índice.
+pub fn page_clipboard_content(query: &PageQuery) -> Result<Vec<ClipboardItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ITEM_COLUMNS} FROM clipboard
+         WHERE deleted_at IS NULL
+           AND (?1 = '' OR (secret = 0 AND content LIKE ?2 ESCAPE '\\') OR source_app LIKE ?2 ESCAPE '\\')
+           AND (?3 = 0 OR favorite = 1)
+           AND (?4 IS NULL OR type = ?4)
+           AND (?5 IS NULL OR kind = ?5)
+           AND (created_at, id) < (COALESCE(?6, '9999'), COALESCE(?7, 0))
+           AND {}
+         ORDER BY created_at DESC, id DESC LIMIT ?8",
+        has_tags(9)
+    ))?;
+    let mut terms = SearchTerms::parse(&query.search);
+    terms.tags.extend(query.tag.clone());
+    let after = query.after.as_ref();
+    let rows = stmt.query_map(
+        params![
+            terms.text,
+            like_pattern(&terms.text),
+            query.favorites,
+            query.content_type,
+            query.kind.map(Kind::as_str),
+            after.map(|after| &after.created_at),
+            after.map(|after| after.id),
+            sql_limit(query.limit),
+            serde_json::to_string(&terms.tags)?,
+        ],
+        ClipboardItem::from_row,
+    )?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+pub fn get_clipboard_item(id: i32) -> Result<Option<ClipboardItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let item = conn
+        .query_row(
+            &format!("SELECT {ITEM_COLUMNS} FROM clipboard WHERE id = ?1 AND deleted_at IS NULL"),
+            [id],
+            ClipboardItem::from_row,
+        )
+        .optional()?;
+    Ok(item)
+}
+
+// Contenido binario de una imagen.
+pub fn get_clipboard_data(id: i32) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let conn = connect()?;
+    let data = conn
+        .query_row("SELECT data FROM clipboard WHERE id = ?1", [id], |row| row.get(0))
+        .optional()?;
+    Ok(data.flatten())
+}
+
+// Lo manda a la papelera.
+pub fn delete_clipboard_item(id: i32) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    Ok(conn.execute(
+        "UPDATE clipboard SET deleted_at = datetime('now') WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+    )? > 0)
+}
+
+// Qué borrar del historial; sin nada elegido, todo. Las condiciones se suman.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClearScope {
+    #[serde(default)]
+    pub keep_favorites: bool,
+    // "text", "image" o "files".
+    #[serde(default)]
+    pub content_type: Option<String>,
+    // Solo lo copiado en los últimos segundos.
+    #[serde(default)]
+    pub last_seconds: Option<u64>,
+    #[serde(default)]
+    pub source_app: Option<String>,
+}
+
+impl ClearScope {
+    fn params(&self) -> (bool, Option<&str>, Option<String>, Option<&str>) {
+        (
+            self.keep_favorites,
+            self.content_type.as_deref(),
+            self.last_seconds.map(|seconds| format!("-{seconds} seconds")),
+            self.source_app.as_deref(),
+        )
+    }
+}
+
+// Lo que abarca un `ClearScope`, con sus valores en ?1 a ?4.
+const CLEARS: &str = "(?1 = 0 OR favorite = 0)
+    AND (?2 IS NULL OR type = ?2)
+    AND (?3 IS NULL OR created_at >= datetime('now', ?3))
+    AND (?4 IS NULL OR source_app = ?4 COLLATE NOCASE)";
+
+// Manda a la papelera lo que abarca `scope`. Devuelve los ids para poder
+// notificarlos y deshacerlo.
+pub fn clear_clipboard_content(scope: &ClearScope) -> Result<Vec<i32>, Box<dyn Error>> {
+    let conn = connect()?;
+    let ids = conn
+        .prepare(&format!(
+            "UPDATE clipboard SET deleted_at = datetime('now') WHERE deleted_at IS NULL AND {CLEARS} RETURNING id"
+        ))?
+        .query_map(scope.params(), |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+// Borra para siempre lo que abarca `scope`, también si ya estaba en la papelera,
+// sin dejar rastros en el archivo: el contenido se pisa antes de borrarlo, SQLite
+// pone en cero lo liberado y VACUUM reescribe la base sin las páginas libres.
+pub fn wipe_clipboard_content(scope: &ClearScope) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut conn = connect()?;
+    conn.execute_batch("PRAGMA secure_delete = ON")?;
+    let tx = conn.transaction()?;
+    let ids = tx
+        .prepare(&format!(
+            "UPDATE clipboard SET content = '', data = NULL, source_app = NULL, hash = NULL WHERE {CLEARS} RETURNING id"
+        ))?
+        .query_map(scope.params(), |row| row.get(0))?
+        .collect::<Result<Vec<i32>>>()?;
+    tx.execute("DELETE FROM clipboard WHERE id IN (SELECT value FROM json_each(?1))", [serde_json::to_string(&ids)?])?;
+    tx.execute("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM clipboard_tags)", [])?;
+    tx.commit()?;
+    // Con WAL, las versiones viejas de las páginas quedan en el log hasta vaciarlo.
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.execute_batch("VACUUM")?;
+    Ok(ids)
+}
+
+// Un período como "15m", "2 hours" o "7d", en segundos.
+pub fn parse_period(period: &str) -> Result<u64, String> {
+    let period = period.trim();
+    let split = period.find(|c: char| !c.is_ascii_digit()).unwrap_or(period.len());
+    let (count, unit) = period.split_at(split);
+    let invalid = || format!("invalid period {period:?}, expected something like 15m, 2h or 7d");
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim() {
+        "s" | "sec" | "second" | "seconds" => 1,
+        "m" | "min" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3_600,
+        "d" | "day" | "days" => 86_400,
+        "w" | "week" | "weeks" => 604_800,
+        unit => return Err(format!("unknown time unit {unit:?} in {period:?}, expected s, m, h, d or w")),
+    };
+    count.checked_mul(seconds).ok_or_else(invalid)
+}
+
+// Elimina lo que excede los límites del historial, salvo los favoritos y lo que
+// tiene etiquetas. Las etiquetas de `expire_tags` no protegen a sus elementos.
+pub fn apply_retention(
+    max_items: Option<usize>,
+    max_age_days: Option<u32>,
+    expire_tags: &[String],
+) -> Result<Vec<i32>, Box<dyn Error>> {
+    let conn = connect()?;
+    let expire_tags = serde_json::to_string(expire_tags)?;
+    let mut ids = Vec::new();
+    if let Some(days) = max_age_days {
+        let mut stmt = conn.prepare(&format!(
+            "DELETE FROM clipboard WHERE {EXPIRES} AND created_at < datetime('now', ?1) RETURNING id"
+        ))?;
+        let rows = stmt.query_map(params![format!("-{days} days"), expire_tags], |row| row.get(0))?;
+        ids.extend(rows.collect::<Result<Vec<i32>>>()?);
+    }
+    if let Some(max_items) = max_items {
+        let mut stmt = conn.prepare(&format!(
+            "DELETE FROM clipboard WHERE {EXPIRES} AND id NOT IN (
+                SELECT id FROM clipboard WHERE {EXPIRES}
+                ORDER BY created_at DESC, id DESC LIMIT ?1
+            ) RETURNING id"
+        ))?;
+        let rows = stmt.query_map(params![max_items as i64, expire_tags], |row| row.get(0))?;
+        ids.extend(rows.collect::<Result<Vec<i32>>>()?);
+    }
+    Ok(ids)
+}
+
+// Un elemento en la papelera y cuándo se borró.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedItem {
+    pub item: ClipboardItem,
+    pub deleted_at: String,
+}
+
+// La papelera, de lo último borrado a lo primero.
+pub fn list_trash() -> Result<Vec<TrashedItem>, Box<dyn Error>> {
+    let conn = connect()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ITEM_COLUMNS}, deleted_at FROM clipboard WHERE deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC, created_at DESC, id DESC"
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashedItem {
+            item: ClipboardItem::from_row(row)?,
+            deleted_at: row.get(11)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+// Saca elementos de la papelera. Devuelve los que estaban en ella.
+pub fn restore_clipboard_items(ids: &[i32]) -> Result<Vec<i32>, Box<dyn Error>> {
+    let conn = connect()?;
+    let ids = conn
+        .prepare(
+            "UPDATE clipboard SET deleted_at = NULL
+             WHERE deleted_at IS NOT NULL AND id IN (SELECT value FROM json_each(?1)) RETURNING id",
+        )?
+        .query_map([serde_json::to_string(ids)?], |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+// Borra definitivamente lo que está en la papelera; con `older_than_days`, solo lo
+// borrado hace más de esos días. Devuelve cuántos elementos se borraron.
+pub fn empty_trash(older_than_days: Option<u32>) -> Result<usize, Box<dyn Error>> {
+    let conn = connect()?;
+    let removed = conn.execute(
+        "DELETE FROM clipboard WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < datetime('now', ?1))",
+        [older_than_days.map(|days| format!("-{days} days"))],
+    )?;
+    conn.execute("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM clipboard_tags)", [])?;
+    Ok(removed)
+}
+
+// Lo que la retención puede borrar; ?2 es el arreglo JSON de `expire_tags`. La
+// papelera se purga aparte.
+const EXPIRES: &str = "deleted_at IS NULL AND favorite = 0 AND NOT EXISTS (
+    SELECT 1 FROM clipboard_tags JOIN tags ON tags.id = clipboard_tags.tag_id
+    WHERE clipboard_tags.clipboard_id = clipboard.id
+      AND NOT EXISTS (SELECT 1 FROM json_each(?2) AS expiring WHERE tags.name = expiring.value)
+)";
+
+pub fn set_favorite(id: i32, favorite: bool) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    Ok(conn.execute(
+        "UPDATE clipboard SET favorite = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, favorite],
+    )? > 0)
+}
+
+// Solo se puede editar el texto; las imágenes y las listas de archivos no.
+pub fn update_clipboard_content(id: i32, content: &str) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    let classification = kind::classify(content);
+    Ok(conn.execute(
+        "UPDATE clipboard SET content = ?2,
+             kind = CASE WHEN secret = 0 THEN ?3 END,
+             language = CASE WHEN secret = 0 THEN ?4 END,
+             hash = ?5
+         WHERE id = ?1 AND type = 'text' AND deleted_at IS NULL",
+        params![
+            id,
+            content,
+            classification.kind.as_str(),
+            classification.language,
+            content_hash("text", content, None)
+        ],
+    )? > 0)
+}
+
+// Una etiqueta y cuántos elementos la tienen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub items: usize,
+}
+
+// Quita los espacios de los extremos. Las comas separan etiquetas al exportar y
+// las comillas delimitan `tag:"..."` en la búsqueda, así que no se aceptan.
+pub fn tag_name(name: &str) -> Result<&str, Box<dyn Error>> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("empty tag name".into());
+    }
+    if name.contains([',', '"']) {
+        return Err(format!("tag names cannot contain commas or quotes: {name:?}").into());
+    }
+    Ok(name)
+}
+
+// Devuelve false si el elemento no existe.
+pub fn tag_item(id: i32, tag: &str) -> Result<bool, Box<dyn Error>> {
+    let tag = tag_name(tag)?;
+    let conn = connect()?;
+    if !conn.prepare("SELECT 1 FROM clipboard WHERE id = ?1 AND deleted_at IS NULL")?.exists([id])? {
+        return Ok(false);
+    }
+    conn.execute("INSERT INTO tags (name) VALUES (?1) ON CONFLICT (name) DO NOTHING", [tag])?;
+    conn.execute(
+        "INSERT OR IGNORE INTO clipboard_tags (clipboard_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2",
+        params![id, tag],
+    )?;
+    Ok(true)
+}
+
+// Devuelve false si el elemento no tenía la etiqueta. Las etiquetas que quedan
+// sin elementos desaparecen.
+pub fn untag_item(id: i32, tag: &str) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    let removed = conn.execute(
+        "DELETE FROM clipboard_tags WHERE clipboard_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+        params![id, tag.trim()],
+    )?;
+    conn.execute("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM clipboard_tags)", [])?;
+    Ok(removed > 0)
+}
+
+// Las etiquetas en uso, en orden alfabético.
+pub fn list_tags() -> Result<Vec<Tag>, Box<dyn Error>> {
+    let conn = connect()?;
+    let tags = conn
+        .prepare(
+            "SELECT tags.name, COUNT(*) FROM tags
+             JOIN clipboard_tags ON clipboard_tags.tag_id = tags.id
+             JOIN clipboard ON clipboard.id = clipboard_tags.clipboard_id AND clipboard.deleted_at IS NULL
+             GROUP BY tags.id ORDER BY tags.name",
+        )?
+        .query_map([], |row| {
+            Ok(Tag {
+                name: row.get(0)?,
+                items: row.get::<_, i64>(1)? as usize,
+            })
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub id: i32,
+    pub name: String,
+    pub body: String,
+    pub created_at: String,
+}
+
+const SNIPPET_COLUMNS: &str = "id, name, body, created_at";
+
+impl Snippet {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Snippet {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            body: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+}
+
+pub fn add_snippet(name: &str, body: &str) -> Result<i32, Box<dyn Error>> {
+    let conn = connect()?;
+    conn.execute("INSERT INTO snippets (name, body) VALUES (?1, ?2)", params![name, body])
+        .map_err(|err| snippet_error(err, name))?;
+    Ok(conn.last_insert_rowid() as i32)
+}
+
+// Cambia el nombre, el cuerpo o ambos. Devuelve false si no existe.
+pub fn update_snippet(id: i32, name: Option<&str>, body: Option<&str>) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    let updated = conn
+        .execute(
+            "UPDATE snippets SET name = COALESCE(?2, name), body = COALESCE(?3, body) WHERE id = ?1",
+            params![id, name, body],
+        )
+        .map_err(|err| snippet_error(err, name.unwrap_or_default()))?;
+    Ok(updated > 0)
+}
+
+fn snippet_error(err: rusqlite::Error, name: &str) -> Box<dyn Error> {
+    match err.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::ConstraintViolation) => format!("there is already a snippet named {name:?}").into(),
+        _ => err.into(),
+    }
+}
+
+pub fn delete_snippet(id: i32) -> Result<bool, Box<dyn Error>> {
+    let conn = connect()?;
+    Ok(conn.execute("DELETE FROM snippets WHERE id = ?1", [id])? > 0)
+}
+
+// Por id o por nombre.
+pub fn find_snippet(id_or_name: &str) -> Result<Option<Snippet>, Box<dyn Error>> {
+    let conn = connect()?;
+    let snippet = conn
+        .query_row(
+            &format!("SELECT {SNIPPET_COLUMNS} FROM snippets WHERE id = ?1 OR name = ?2 ORDER BY name = ?2 DESC LIMIT 1"),
+            params![id_or_name.parse::<i32>().ok(), id_or_name.trim()],
+            Snippet::from_row,
+        )
+        .optional()?;
+    Ok(snippet)
+}
+
+// Los snippets cuyo nombre o cuerpo contienen `query`, por nombre.
+pub fn search_snippets(query: &str) -> Result<Vec<Snippet>, Box<dyn Error>> {
+    let conn = connect()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SNIPPET_COLUMNS} FROM snippets
+         WHERE name LIKE ?1 ESCAPE '\\' OR body LIKE ?1 ESCAPE '\\'
+         ORDER BY name"
+    ))?;
+    let rows = stmt.query_map([like_pattern(query.trim())], Snippet::from_row)?;
+    Ok(rows.collect::<Result<Vec<_>>>()?)
+}
+
+// "AAAA-MM-DD HH:MM:SS" en la hora local, para los marcadores de los snippets.
+pub fn local_time() -> Result<String, Box<dyn Error>> {
+    let conn = connect()?;
+    Ok(conn.query_row("SELECT datetime('now', 'localtime')", [], |row| row.get(0))?)
+}
+
+pub fn count_clipboard_items() -> Result<usize, Box<dyn Error>> {
+    let conn = connect()?;
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clipboard WHERE deleted_at IS NULL", [], |row| row.get(0))?;
+    Ok(count as usize)
+}
+
+// Detecta cambios hechos en la base de datos por otras conexiones, sin releerla.
+pub struct ChangeWatcher {
+    conn: Connection,
+    version: i64,
+}
+
+impl ChangeWatcher {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let conn = connect()?;
+        let version = data_version(&conn)?;
+        Ok(ChangeWatcher { conn, version })
+    }
+
+    pub fn changed(&mut self) -> Result<bool, Box<dyn Error>> {
+        let version = data_version(&self.conn)?;
+        let changed = version != self.version;
+        self.version = version;
+        Ok(changed)
+    }
+}
+
+// Cambia cada vez que otra conexión confirma una transacción.
+fn data_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+}
+
+// SQLite interpreta un LIMIT negativo como "sin límite".
+fn sql_limit(limit: Option<usize>) -> i64 {
+    limit.map_or(-1, |limit| limit as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_terms() {
+        let terms = SearchTerms::parse(r#"tag:sql select  tag:"customer emails" from"#);
+        assert_eq!(terms.text, "select from");
+        assert_eq!(terms.tags, ["sql", "customer emails"]);
+        assert_eq!(SearchTerms::parse("hashtag:x").tags, Vec::<String>::new());
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(parse_period("15m"), Ok(900));
+        assert_eq!(parse_period("2 hours"), Ok(7_200));
+        assert_eq!(parse_period("1d"), Ok(86_400));
+        assert!(parse_period("m").is_err());
+        assert!(parse_period("3 fortnights").is_err());
+        assert!(parse_period("30000000000000000w").is_err());
+    }
 }
@@ -0,0 +1,67 @@
+use iced::widget::image::Handle;
+use image::{ImageFormat, RgbaImage};
+
+use crate::db;
+
+// Lado mayor de las miniaturas, en píxeles.
+const THUMBNAIL_SIZE: u32 = 96;
+
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub handle: Handle,
+    pub width: u32,
+    pub height: u32,
+    // Tamaño del PNG guardado, en bytes.
+    pub size: usize,
+}
+
+// Decodificar un PNG grande lleva tiempo, así que se hace fuera del hilo de la interfaz.
+pub async fn thumbnail(id: i32) -> Option<Thumbnail> {
+    async_std::task::spawn_blocking(move || {
+        let data = db::get_clipboard_data(id).ok()??;
+        let image = decode(&data)?;
+        let (width, height) = scaled(image.width(), image.height());
+        let small = image::imageops::thumbnail(&image, width, height);
+        Some(Thumbnail {
+            handle: Handle::from_rgba(small.width(), small.height(), small.into_raw()),
+            width: image.width(),
+            height: image.height(),
+            size: data.len(),
+        })
+    })
+    .await
+}
+
+pub async fn full_image(id: i32) -> Option<Handle> {
+    async_std::task::spawn_blocking(move || {
+        let data = db::get_clipboard_data(id).ok()??;
+        let image = decode(&data)?;
+        Some(Handle::from_rgba(image.width(), image.height(), image.into_raw()))
+    })
+    .await
+}
+
+fn decode(data: &[u8]) -> Option<RgbaImage> {
+    let image = image::load_from_memory_with_format(data, ImageFormat::Png)
+        .inspect_err(|err| tracing::warn!("cannot decode clipboard image: {err}"))
+        .ok()?;
+    Some(image.to_rgba8())
+}
+
+// Mantiene la proporción con el lado mayor en THUMBNAIL_SIZE.
+fn scaled(width: u32, height: u32) -> (u32, u32) {
+    let longest = width.max(height).max(1);
+    if longest <= THUMBNAIL_SIZE {
+        return (width.max(1), height.max(1));
+    }
+    let scale = |side: u32| ((side as u64 * THUMBNAIL_SIZE as u64 / longest as u64) as u32).max(1);
+    (scale(width), scale(height))
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..1_024 => format!("{bytes} B"),
+        1_024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1_024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
@@ -0,0 +1,99 @@
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+
+use crate::config::{Config, KeyCombo, KEY_ACTIONS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    FocusNext,
+    FocusPrevious,
+    SelectNext,
+    SelectPrevious,
+    Copy,
+    PastePlain,
+    Delete,
+    Pin,
+    // Elige el elemento visible en esa posición, de 0 a 8.
+    Slot(usize),
+    TogglePreview,
+    // Saca de la papelera lo último borrado.
+    Undo,
+    Close,
+}
+
+impl KeyAction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "focus-next" => Some(KeyAction::FocusNext),
+            "focus-previous" => Some(KeyAction::FocusPrevious),
+            "select-next" => Some(KeyAction::SelectNext),
+            "select-previous" => Some(KeyAction::SelectPrevious),
+            "copy" => Some(KeyAction::Copy),
+            "paste-plain" => Some(KeyAction::PastePlain),
+            "delete" => Some(KeyAction::Delete),
+            "pin" => Some(KeyAction::Pin),
+            "toggle-preview" => Some(KeyAction::TogglePreview),
+            "undo" => Some(KeyAction::Undo),
+            "close" => Some(KeyAction::Close),
+            _ => {
+                let slot: usize = name.strip_prefix("slot-")?.parse().ok()?;
+                (1..=9).contains(&slot).then(|| KeyAction::Slot(slot - 1))
+            }
+        }
+    }
+}
+
+// Atajos de teclado de la configuración, o los de por defecto.
+#[derive(Debug, Clone, Default)]
+pub struct KeyMap {
+    bindings: Vec<(KeyAction, KeyCombo)>,
+}
+
+impl KeyMap {
+    pub fn new(config: &Config) -> Self {
+        let bindings = KEY_ACTIONS
+            .iter()
+            .filter_map(|(name, _)| Some((KeyAction::from_name(name)?, config.key_combos(name))))
+            .flat_map(|(action, combos)| combos.into_iter().map(move |combo| (action, combo)))
+            .collect();
+        KeyMap { bindings }
+    }
+
+    pub fn action(&self, key: &Key, modifiers: Modifiers) -> Option<KeyAction> {
+        let name = key_name(key)?;
+        self.bindings
+            .iter()
+            .find(|(_, combo)| {
+                combo.key == name
+                    && combo.ctrl == modifiers.control()
+                    && combo.alt == modifiers.alt()
+                    && combo.shift == modifiers.shift()
+                    && combo.logo == modifiers.logo()
+            })
+            .map(|(action, _)| *action)
+    }
+}
+
+// Nombre de la tecla tal como se escribe en la configuración.
+fn key_name(key: &Key) -> Option<String> {
+    let name = match key {
+        Key::Character(c) => return Some(c.to_lowercase()),
+        Key::Named(Named::Tab) => "tab",
+        Key::Named(Named::Enter) => "enter",
+        Key::Named(Named::Escape) => "escape",
+        Key::Named(Named::Space) => "space",
+        Key::Named(Named::Backspace) => "backspace",
+        Key::Named(Named::Delete) => "delete",
+        Key::Named(Named::ArrowUp) => "up",
+        Key::Named(Named::ArrowDown) => "down",
+        Key::Named(Named::ArrowLeft) => "left",
+        Key::Named(Named::ArrowRight) => "right",
+        Key::Named(Named::Home) => "home",
+        Key::Named(Named::End) => "end",
+        Key::Named(Named::PageUp) => "pageup",
+        Key::Named(Named::PageDown) => "pagedown",
+        Key::Named(named) => return Some(format!("{named:?}").to_lowercase()),
+        Key::Unidentified => return None,
+    };
+    Some(name.to_string())
+}
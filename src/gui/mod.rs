@@ -1,16 +1,76 @@
-use iced::keyboard;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream, StreamExt};
+use iced::{event, keyboard};
 use iced::widget::{
-    self, button, center, checkbox, column, container, keyed_column, row,
-    scrollable, text, text_input, Text,};
-use iced::window;
-use iced::{Center, Element, Fill, Font, Subscription, Task as Command};
+    self, button, center, checkbox, column, container, image, keyed_column, pick_list, row,
+    scrollable, text, text_input, Text,
+};
+use iced::{stream, window};
+use iced::{Center, ContentFit, Element, Fill, FillPortion, Font, Subscription, Task as Command};
+
+mod images;
+mod keys;
+mod popup;
 
-use serde::{Deserialize, Serialize};
-use crate::db::get_clipboard_content;
+use crate::actions::{self, Action};
+use crate::client::{self, HistoryChange};
+use crate::config::Config;
+use crate::db::{self, ClearScope, ClipboardItem, PageCursor, PageQuery, SearchTerms, Snippet};
+use crate::ipc::{Event, Status};
+use crate::kind::Kind;
+use crate::preview::{self, Preview};
+use crate::save::{self, ImageFormat};
+use crate::snippets::Template;
+use crate::transform::{Pipeline, Transform};
+use self::images::Thumbnail;
+use self::keys::{KeyAction, KeyMap};
 
-// Code from https://github.com/iced-rs/iced/blob/master/examples/todos/src/main.rs for change.
-pub fn show() -> iced::Result {
+pub fn show(config: Config) -> iced::Result {
     tracing_subscriber::fmt::init();
+    run(config, Mode::Window)
+}
+
+// Ventana para elegir un elemento y volver a lo que se estaba haciendo: se
+// cierra con Escape o al perder el foco.
+pub fn popup(config: Config) -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
+    if popup::has_layer_shell() {
+        return Ok(run_layer(config)?);
+    }
+    Ok(run(config, Mode::Popup)?)
+}
+
+fn run_layer(config: Config) -> iced_layershell::Result {
+    let theme = config.theme();
+    iced_layershell::build_pattern::application(
+        popup::APP_ID,
+        MainWindows::update,
+        MainWindows::view,
+    )
+        .subscription(MainWindows::subscription)
+        .theme(move |_| theme.clone())
+        .font(include_bytes!("./fonts/icons.ttf").as_slice())
+        .layer_settings(popup::layer_settings())
+        .run_with(move || MainWindows::new(config, Mode::Popup))
+}
+
+fn run(config: Config, mode: Mode) -> iced::Result {
+    let theme = config.theme();
+    let window = match mode {
+        Mode::Window => window::Settings {
+            size: (450.0, 650.0).into(),
+            resizable: false,
+            ..window::Settings::default()
+        },
+        Mode::Popup => popup::window_settings(config.gui.popup_position),
+    };
 
     iced::application(
         "Ropias",
@@ -18,340 +78,1356 @@ pub fn show() -> iced::Result {
         MainWindows::view,
     )
         .subscription(MainWindows::subscription)
+        .theme(move |_| theme.clone())
         .font(include_bytes!("./fonts/icons.ttf").as_slice())
-        .window_size((450.0, 650.0))
-        .resizable(false)
-        .run_with(MainWindows::new)
+        .window(window)
+        .run_with(move || MainWindows::new(config, mode))
+}
+
+const SEARCH_INPUT: &str = "search";
+
+// El historial se carga por páginas a medida que se baja por la lista.
+const PAGE_SIZE: usize = 50;
+// Se pide la página siguiente al acercarse a este margen del final.
+const LOAD_MORE_ROWS: usize = 10;
+const LOAD_MORE_OFFSET: f32 = 0.8;
+
+// Cuánto se muestra el aviso para deshacer un borrado.
+const UNDO_TIMEOUT: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Mode {
+    #[default]
+    Window,
+    Popup,
+}
+
+// Lo que no depende del historial cargado.
+#[derive(Debug, Clone, Default)]
+struct Context {
+    keys: KeyMap,
+    mode: Mode,
+    // El popup se cierra al perder el foco solo si llegó a tenerlo.
+    focused: bool,
+    presets: BTreeMap<String, Vec<String>>,
+}
+
+impl Context {
+    fn focus_changed(&mut self, focused: bool) -> Command<Message> {
+        if focused {
+            self.focused = true;
+            text_input::focus(SEARCH_INPUT)
+        } else if self.mode == Mode::Popup && self.focused {
+            iced::exit()
+        } else {
+            Command::none()
+        }
+    }
 }
 
 enum MainWindows {
-    Loading,
-    Loaded(State),
+    Loading(Context),
+    Loaded(Box<State>),
+}
+
+#[derive(Debug, Clone)]
+struct SnippetPrompt {
+    snippet: Snippet,
+    // Etiqueta y respuesta, en orden de aparición.
+    answers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ClearForm {
+    preset: ClearPreset,
+    // Solo lo copiado desde esa aplicación, si no está vacío.
+    app: String,
+    secure: bool,
+}
+
+impl ClearForm {
+    fn scope(&self) -> ClearScope {
+        let mut scope = self.preset.scope();
+        let app = self.app.trim();
+        scope.source_app = (!app.is_empty()).then(|| app.to_string());
+        scope
+    }
+}
+
+// Lo que se puede elegir borrar desde la GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClearPreset {
+    #[default]
+    Everything,
+    NonFavorites,
+    Text,
+    Images,
+    Files,
+    LastMinutes,
+    LastHour,
+    LastDay,
+}
+
+impl ClearPreset {
+    const ALL: [ClearPreset; 8] = [
+        ClearPreset::Everything,
+        ClearPreset::NonFavorites,
+        ClearPreset::Text,
+        ClearPreset::Images,
+        ClearPreset::Files,
+        ClearPreset::LastMinutes,
+        ClearPreset::LastHour,
+        ClearPreset::LastDay,
+    ];
+
+    fn scope(self) -> ClearScope {
+        let content_type = |content_type: &str| ClearScope {
+            content_type: Some(content_type.to_string()),
+            ..ClearScope::default()
+        };
+        let last = |seconds| ClearScope {
+            last_seconds: Some(seconds),
+            ..ClearScope::default()
+        };
+        match self {
+            ClearPreset::Everything => ClearScope::default(),
+            ClearPreset::NonFavorites => ClearScope {
+                keep_favorites: true,
+                ..ClearScope::default()
+            },
+            ClearPreset::Text => content_type("text"),
+            ClearPreset::Images => content_type("image"),
+            ClearPreset::Files => content_type("files"),
+            ClearPreset::LastMinutes => last(15 * 60),
+            ClearPreset::LastHour => last(3_600),
+            ClearPreset::LastDay => last(86_400),
+        }
+    }
+}
+
+impl fmt::Display for ClearPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ClearPreset::Everything => "Everything",
+            ClearPreset::NonFavorites => "Everything but favorites",
+            ClearPreset::Text => "Text",
+            ClearPreset::Images => "Images",
+            ClearPreset::Files => "Files",
+            ClearPreset::LastMinutes => "Last 15 minutes",
+            ClearPreset::LastHour => "Last hour",
+            ClearPreset::LastDay => "Last 24 hours",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Undo {
+    ids: Vec<i32>,
+    // Si vino de "Clear history".
+    cleared: bool,
 }
 
 #[derive(Debug, Default)]
 struct State {
-    input_value: String,
+    query: String,
     filter: Filter,
-    tasks: Vec<ClipboardItemUI>,
-    dirty: bool,
+    kind: Option<Kind>,
+    // Etiqueta elegida en la barra de etiquetas.
+    tag: Option<String>,
+    // Las etiquetas en uso, para la barra.
+    tags: Vec<String>,
+    // Snippets que coinciden con la búsqueda, sobre el historial.
+    snippets: Vec<Snippet>,
+    // Snippet con preguntas `{{input:...}}` esperando las respuestas.
+    prompt: Option<SnippetPrompt>,
+    items: Vec<ClipboardItemUI>,
+    status: Option<Status>,
+    context: Context,
+    // Último error de una operación, se muestra bajo la cabecera
+    error: Option<String>,
+    // Cambios pendientes de guardar, en orden, de a uno por vez
+    pending: VecDeque<Change>,
     saving: bool,
+    // Borrar el historial pide elegir qué y confirmarlo.
+    clearing: Option<ClearForm>,
+    // Lo último que se mandó a la papelera, mientras se puede deshacer.
+    undo: Option<Undo>,
+    // Cambia con cada borrado, para que solo el último aviso venza.
+    undo_serial: u64,
+    // Posición del elemento elegido con el teclado entre los visibles
+    selected: usize,
+    // Miniaturas por id; None mientras se decodifica o si no se pudo.
+    thumbnails: HashMap<i32, Option<Thumbnail>>,
+    show_preview: bool,
+    // Imagen completa del elemento elegido, para el panel de vista previa.
+    preview: Option<(i32, Option<image::Handle>)>,
+    // Cambia con la búsqueda o el filtro, para descartar páginas de consultas viejas
+    generation: u64,
+    // No quedan más páginas
+    complete: bool,
+    loading_more: bool,
+}
+
+impl State {
+    // Aplica lo que hizo el daemon sin tocar la búsqueda ni el desplazamiento.
+    // Los cambios hechos desde esta ventana también llegan y no tienen efecto.
+    fn apply(&mut self, event: Event) {
+        match event {
+            // Lo que sale de la papelera vuelve a su lugar, que no siempre es el primero.
+            Event::Added { item } => {
+                let item = ClipboardItemUI::new(item);
+                let shown = self.items.iter().any(|shown| shown.item.id == item.item.id);
+                if !shown && self.shows(&item, &self.search_terms()) {
+                    let key = (item.item.created_at.as_str(), item.item.id);
+                    match self.items.iter().position(|shown| (shown.item.created_at.as_str(), shown.item.id) < key) {
+                        Some(at) => self.items.insert(at, item),
+                        // Más viejo que lo cargado: llegará con su página.
+                        None if !self.complete => {}
+                        None => self.items.push(item),
+                    }
+                }
+            }
+            Event::Updated { item } => {
+                self.remember_tags(&item.tags);
+                if let Some(shown) = self.items.iter_mut().find(|shown| shown.item.id == item.id) {
+                    // No se pisa lo que el usuario está editando.
+                    if let ClipboardItemState::Idle = shown.state {
+                        shown.item = item;
+                    }
+                }
+            }
+            Event::Removed { id } => {
+                self.items.retain(|shown| shown.item.id != id);
+                self.thumbnails.remove(&id);
+            }
+            Event::Status { status } => self.status = Some(status),
+        }
+    }
+
+    // Índices en `items` de lo que se ve con la búsqueda y el filtro actuales.
+    fn visible(&self) -> Vec<usize> {
+        let terms = self.search_terms();
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| self.shows(item, &terms))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // La búsqueda con el texto en minúsculas y la etiqueta de la barra sumada a las de `tag:`.
+    fn search_terms(&self) -> SearchTerms {
+        let mut terms = SearchTerms::parse(&self.query);
+        terms.text = terms.text.to_lowercase();
+        terms.tags.extend(self.tag.clone());
+        terms
+    }
+
+    fn shows(&self, item: &ClipboardItemUI, terms: &SearchTerms) -> bool {
+        self.filter.matches(item)
+            && self.kind.is_none_or(|kind| item.item.kind == Some(kind))
+            && terms.tags.iter().all(|tag| item.has_tag(tag))
+            && item.matches(&terms.text)
+    }
+
+    // Suma a la barra las etiquetas nuevas de un elemento.
+    fn remember_tags(&mut self, tags: &[String]) {
+        for tag in tags {
+            if !self.tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self.tags.sort_by_key(|tag| tag.to_lowercase());
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let visible = self.visible();
+        visible.get(self.selected.min(visible.len().saturating_sub(1))).copied()
+    }
+
+    // Mueve la selección y desplaza la lista para que se vea.
+    fn select(&mut self, selected: usize) -> Command<Message> {
+        let count = self.visible().len();
+        self.selected = selected.min(count.saturating_sub(1));
+        let y = if count > 1 {
+            self.selected as f32 / (count - 1) as f32
+        } else {
+            0.0
+        };
+        let more = if self.selected + LOAD_MORE_ROWS >= count {
+            self.load_more()
+        } else {
+            Command::none()
+        };
+        Command::batch(vec![
+            scrollable::snap_to(items_scrollable(), scrollable::RelativeOffset { x: 0.0, y }),
+            more,
+        ])
+    }
+
+    fn page_query(&self, after: Option<PageCursor>, limit: usize) -> PageQuery {
+        PageQuery {
+            search: self.query.clone(),
+            favorites: self.filter == Filter::Favorites,
+            content_type: self.filter.content_type().map(String::from),
+            kind: self.kind,
+            tag: self.tag.clone(),
+            after,
+            limit: Some(limit),
+        }
+    }
+
+    // Vuelve a pedir el historial desde el principio. Con `keep_loaded` se piden
+    // tantos elementos como ya hay, para no perder el desplazamiento.
+    fn reload(&mut self, keep_loaded: bool) -> Command<Message> {
+        self.generation += 1;
+        self.loading_more = false;
+        let limit = if keep_loaded {
+            self.items.len().max(PAGE_SIZE)
+        } else {
+            PAGE_SIZE
+        };
+        let generation = self.generation;
+        Command::perform(SavedState::load(self.page_query(None, limit)), move |result| {
+            Message::Loaded(generation, result)
+        })
+    }
+
+    fn load_more(&mut self) -> Command<Message> {
+        if self.complete || self.loading_more {
+            return Command::none();
+        }
+        let Some(last) = self.items.last() else {
+            return Command::none();
+        };
+        self.loading_more = true;
+        let generation = self.generation;
+        let query = self.page_query(Some(PageCursor::from(&last.item)), PAGE_SIZE);
+        Command::perform(SavedState::load(query), move |result| {
+            Message::MoreLoaded(generation, result)
+        })
+    }
+
+    fn item_message(&mut self, i: usize, message: ClipboardItemMessage) -> Command<Message> {
+        match message {
+            ClipboardItemMessage::Copy => match self.items.get(i) {
+                Some(item) => Command::perform(copy(item.item.id, false), Message::Copied),
+                None => Command::none(),
+            },
+            ClipboardItemMessage::Menu(entry) => {
+                let Some(item) = self.items.get(i) else {
+                    return Command::none();
+                };
+                match entry {
+                    MenuEntry::Action(action) => Command::perform(run_action(item.item.id, action), Message::Copied),
+                    MenuEntry::Transform(name) => match Pipeline::parse(&name, &self.context.presets) {
+                        Ok(pipeline) => Command::perform(copy_transformed(item.item.id, pipeline), Message::Copied),
+                        Err(err) => {
+                            self.error = Some(err);
+                            Command::none()
+                        }
+                    },
+                    MenuEntry::SaveAs => {
+                        let path = save::default_dir().join(save::default_name(&item.item, None));
+                        self.items[i].state = ClipboardItemState::Saving(path.display().to_string());
+                        let id = ClipboardItemUI::text_input_id(i);
+                        Command::batch(vec![text_input::focus(id.clone()), text_input::move_cursor_to_end(id)])
+                    }
+                    MenuEntry::AddTag => {
+                        self.items[i].state = ClipboardItemState::Tagging(String::new());
+                        text_input::focus(ClipboardItemUI::text_input_id(i))
+                    }
+                    MenuEntry::RemoveTag(tag) => {
+                        let item = &mut self.items[i];
+                        item.item.tags.retain(|existing| existing != &tag);
+                        self.pending.push_back(Change::Tag(item.item.id, tag, false));
+                        Command::none()
+                    }
+                }
+            }
+            ClipboardItemMessage::AddTag => {
+                let Some(item) = self.items.get_mut(i) else {
+                    return Command::none();
+                };
+                let ClipboardItemState::Tagging(tag) = &item.state else {
+                    return Command::none();
+                };
+                match db::tag_name(tag) {
+                    Ok(tag) => {
+                        let tag = tag.to_string();
+                        item.state = ClipboardItemState::Idle;
+                        if !item.has_tag(&tag) {
+                            item.item.tags.push(tag.clone());
+                            self.pending.push_back(Change::Tag(item.item.id, tag.clone(), true));
+                        }
+                        self.remember_tags(&[tag]);
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+                Command::none()
+            }
+            ClipboardItemMessage::ShowTag(tag) => {
+                self.tag = Some(tag);
+                Command::batch(vec![self.select(0), self.reload(false)])
+            }
+            ClipboardItemMessage::Save => {
+                let Some(item) = self.items.get_mut(i) else {
+                    return Command::none();
+                };
+                match std::mem::take(&mut item.state) {
+                    ClipboardItemState::Saving(path) => {
+                        Command::perform(save_item(item.item.clone(), PathBuf::from(path)), Message::SavedTo)
+                    }
+                    state => {
+                        item.state = state;
+                        Command::none()
+                    }
+                }
+            }
+            ClipboardItemMessage::Delete => {
+                if i < self.items.len() {
+                    let item = self.items.remove(i);
+                    self.pending.push_back(Change::Delete(item.item.id));
+                }
+
+                Command::none()
+            }
+            message => {
+                let Some(item) = self.items.get_mut(i) else {
+                    return Command::none();
+                };
+                let should_focus = matches!(message, ClipboardItemMessage::Edit);
+
+                if let Some(change) = item.update(message) {
+                    self.pending.push_back(change);
+                }
+
+                if should_focus {
+                    let id = ClipboardItemUI::text_input_id(i);
+                    Command::batch(vec![
+                        text_input::focus(id.clone()),
+                        text_input::select_all(id),
+                    ])
+                } else {
+                    Command::none()
+                }
+            }
+        }
+    }
+
+    // Copia el elemento y, si todo va bien, cierra la ventana.
+    fn pick(&self, i: Option<usize>, plain: bool) -> Command<Message> {
+        match i.and_then(|i| self.items.get(i)) {
+            Some(item) => Command::perform(copy(item.item.id, plain), Message::Picked),
+            None => Command::none(),
+        }
+    }
+
+    fn key_action(&mut self, action: KeyAction) -> Command<Message> {
+        match action {
+            KeyAction::FocusNext => widget::focus_next(),
+            KeyAction::FocusPrevious => widget::focus_previous(),
+            KeyAction::SelectNext => self.select(self.selected + 1),
+            KeyAction::SelectPrevious => self.select(self.selected.saturating_sub(1)),
+            KeyAction::Copy => self.pick(self.selected_index(), false),
+            KeyAction::PastePlain => self.pick(self.selected_index(), true),
+            KeyAction::Delete => match self.selected_index() {
+                Some(i) => self.item_message(i, ClipboardItemMessage::Delete),
+                None => Command::none(),
+            },
+            KeyAction::Pin => match self.selected_index() {
+                Some(i) => self.item_message(i, ClipboardItemMessage::ToggleFavorite),
+                None => Command::none(),
+            },
+            KeyAction::Slot(slot) => self.pick(self.visible().get(slot).copied(), false),
+            KeyAction::TogglePreview => {
+                self.show_preview = !self.show_preview;
+                Command::none()
+            }
+            KeyAction::Undo => self.undo(),
+            KeyAction::Close => iced::exit(),
+        }
+    }
+
+    // iced no siempre deja el foco en el campo de búsqueda, así que las teclas que
+    // nadie captura se aplican a la búsqueda y se vuelve a enfocar el campo.
+    fn type_to_search(&mut self, key: &keyboard::Key, modifiers: keyboard::Modifiers, text: Option<&str>) -> Command<Message> {
+        if modifiers.control() || modifiers.alt() || modifiers.logo() {
+            return Command::none();
+        }
+        match key {
+            keyboard::Key::Named(keyboard::key::Named::Backspace) => {
+                self.query.pop();
+            }
+            _ => match text.filter(|text| !text.chars().any(char::is_control)) {
+                Some(text) => self.query.push_str(text),
+                None => return Command::none(),
+            },
+        }
+        Command::batch(vec![
+            self.select(0),
+            self.reload(false),
+            text_input::focus(SEARCH_INPUT),
+            text_input::move_cursor_to_end(SEARCH_INPUT),
+        ])
+    }
+
+    // Pide en segundo plano las miniaturas que faltan y la imagen del elemento elegido.
+    fn load_images(&mut self) -> Command<Message> {
+        let mut commands = Vec::new();
+        for shown in &self.items {
+            let id = shown.item.id;
+            if shown.item.content_type == "image" && !self.thumbnails.contains_key(&id) {
+                self.thumbnails.insert(id, None);
+                commands.push(Command::perform(images::thumbnail(id), move |thumbnail| {
+                    Message::ThumbnailLoaded(id, thumbnail)
+                }));
+            }
+        }
+        let selected = self.selected_index().map(|i| &self.items[i].item);
+        if let Some(item) = selected.filter(|item| self.show_preview && item.content_type == "image") {
+            let id = item.id;
+            if self.preview.as_ref().map(|(preview, _)| *preview) != Some(id) {
+                self.preview = Some((id, None));
+                commands.push(Command::perform(images::full_image(id), move |handle| {
+                    Message::PreviewLoaded(id, handle)
+                }));
+            }
+        }
+        Command::batch(commands)
+    }
+
+    // Saca de la papelera lo último borrado; vuelve a la lista con el evento del
+    // daemon o al releer el historial.
+    fn undo(&mut self) -> Command<Message> {
+        if let Some(undo) = self.undo.take() {
+            self.pending.push_back(Change::Restore(undo.ids));
+        }
+        Command::none()
+    }
+
+    fn next_save(&mut self) -> Command<Message> {
+        if self.saving {
+            return Command::none();
+        }
+        match self.pending.pop_front() {
+            Some(change) => {
+                self.saving = true;
+                Command::perform(SavedState::save(change), Message::Saved)
+            }
+            None => Command::none(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 enum Message {
-    Loaded(Result<SavedState, LoadError>),
-    Saved(Result<(), SaveError>),
-    InputChanged(String),
-    CreateTask,
+    Loaded(u64, Result<SavedState, LoadError>),
+    MoreLoaded(u64, Result<SavedState, LoadError>),
+    Scrolled(scrollable::Viewport),
+    // Con lo que se mandó a la papelera, si se puede deshacer.
+    Saved(Result<Option<Undo>, SaveError>),
+    Undo,
+    UndoExpired(u64),
+    QueryChanged(String),
     FilterChanged(Filter),
-    TaskMessage(usize, ClipboardItemMessage),
-    TabPressed { shift: bool },
-    ToggleFullscreen(window::Mode),
+    KindChanged(KindChoice),
+    TagChanged(Option<String>),
+    Item(usize, ClipboardItemMessage),
+    Copied(Result<(), String>),
+    SavedTo(Result<Vec<PathBuf>, String>),
+    // Copiado desde el teclado: la ventana se cierra.
+    Picked(Result<(), String>),
+    ClearPressed,
+    ClearPresetChanged(ClearPreset),
+    ClearAppChanged(String),
+    ClearSecureToggled(bool),
+    ClearConfirmed,
+    SnippetPressed(usize),
+    SnippetAnswered(usize, String),
+    SnippetNext(usize),
+    SnippetCopy,
+    SnippetCancel,
+    History(HistoryChange),
+    ThumbnailLoaded(i32, Option<Thumbnail>),
+    PreviewLoaded(i32, Option<image::Handle>),
+    FocusChanged(bool),
+    KeyPressed {
+        key: keyboard::Key,
+        modifiers: keyboard::Modifiers,
+        text: Option<String>,
+        status: event::Status,
+    },
 }
 
 impl MainWindows {
-    fn new() -> (Self, Command<Message>) {
+    fn new(config: Config, mode: Mode) -> (Self, Command<Message>) {
+        let context = Context {
+            keys: KeyMap::new(&config),
+            mode,
+            focused: false,
+            presets: config.transforms.clone(),
+        };
         (
-            Self::Loading,
-            Command::perform(SavedState::load(), Message::Loaded),
+            Self::Loading(context),
+            Command::perform(
+                SavedState::load(PageQuery {
+                    limit: Some(PAGE_SIZE),
+                    ..PageQuery::default()
+                }),
+                |result| Message::Loaded(0, result),
+            ),
         )
     }
     fn update(&mut self, message: Message) -> Command<Message> {
         match self {
-            MainWindows::Loading => {
+            MainWindows::Loading(context) => {
                 match message {
-                    Message::Loaded(Ok(state)) => {
-                        *self = MainWindows::Loaded(State {
-                            input_value: state.input_value,
-                            filter: state.filter,
-                            tasks: state.items,
+                    Message::Loaded(_, Ok(state)) => {
+                        *self = MainWindows::Loaded(Box::new(State {
+                            items: state.items,
+                            tags: state.tags,
+                            snippets: state.snippets,
+                            status: state.status,
+                            complete: state.complete,
+                            show_preview: context.mode == Mode::Window,
+                            context: context.clone(),
                             ..State::default()
-                        });
+                        }));
                     }
-                    Message::Loaded(Err(_)) => {
-                        *self = MainWindows::Loaded(State::default());
+                    Message::Loaded(_, Err(err)) => {
+                        *self = MainWindows::Loaded(Box::new(State {
+                            show_preview: context.mode == Mode::Window,
+                            context: context.clone(),
+                            error: Some(err.to_string()),
+                            ..State::default()
+                        }));
                     }
-                    _ => {}
+                    Message::FocusChanged(focused) => return context.focus_changed(focused),
+                    _ => return Command::none(),
                 }
 
-                text_input::focus("new-task")
+                match self {
+                    MainWindows::Loaded(state) => {
+                        Command::batch(vec![text_input::focus(SEARCH_INPUT), state.load_images()])
+                    }
+                    MainWindows::Loading(_) => Command::none(),
+                }
             }
             MainWindows::Loaded(state) => {
-                let mut saved = false;
-
                 let command = match message {
-                    Message::InputChanged(value) => {
-                        state.input_value = value;
+                    Message::QueryChanged(value) => {
+                        state.query = value;
 
-                        Command::none()
+                        Command::batch(vec![state.select(0), state.reload(false)])
+                    }
+                    Message::FilterChanged(filter) => {
+                        state.filter = filter;
+
+                        Command::batch(vec![state.select(0), state.reload(false)])
+                    }
+                    Message::KindChanged(KindChoice(kind)) => {
+                        state.kind = kind;
+
+                        Command::batch(vec![state.select(0), state.reload(false)])
+                    }
+                    Message::TagChanged(tag) => {
+                        state.tag = tag;
+
+                        Command::batch(vec![state.select(0), state.reload(false)])
                     }
-                    Message::CreateTask => {
-                        if !state.input_value.is_empty() {
-                            state
-                                .tasks
-                                .push(ClipboardItemUI::new(state.input_value.clone()));
-                            state.input_value.clear();
+                    Message::Scrolled(viewport) => {
+                        if viewport.relative_offset().y >= LOAD_MORE_OFFSET {
+                            state.load_more()
+                        } else {
+                            Command::none()
                         }
+                    }
+                    Message::Item(i, item_message) => state.item_message(i, item_message),
+                    Message::SavedTo(result) => {
+                        state.error = Some(match result {
+                            Ok(paths) => match paths.as_slice() {
+                                [path] => format!("Saved to {}", path.display()),
+                                paths => format!("Saved {} files", paths.len()),
+                            },
+                            Err(err) => err,
+                        });
 
                         Command::none()
                     }
-                    Message::FilterChanged(filter) => {
-                        state.filter = filter;
+                    Message::Copied(result) => {
+                        state.error = result.err();
+
+                        Command::none()
+                    }
+                    Message::Picked(Ok(())) if state.status.is_some() => iced::exit(),
+                    // Sin daemon lo copiado se pierde al cerrar la ventana.
+                    Message::Picked(Ok(())) => {
+                        state.error = Some(
+                            "Copied. The daemon is not running, keep this window open until you paste"
+                                .to_string(),
+                        );
+
+                        Command::none()
+                    }
+                    Message::Picked(Err(err)) => {
+                        state.error = Some(err);
+
+                        Command::none()
+                    }
+                    Message::ClearPressed => {
+                        state.clearing = match state.clearing {
+                            Some(_) => None,
+                            None => Some(ClearForm::default()),
+                        };
 
                         Command::none()
                     }
-                    Message::TaskMessage(i, ClipboardItemMessage::Delete) => {
-                        state.tasks.remove(i);
+                    Message::ClearPresetChanged(preset) => {
+                        if let Some(form) = &mut state.clearing {
+                            form.preset = preset;
+                        }
 
                         Command::none()
                     }
-                    Message::TaskMessage(i, task_message) => {
-                        if let Some(task) = state.tasks.get_mut(i) {
-                            let should_focus =
-                                matches!(task_message, ClipboardItemMessage::Edit);
+                    Message::ClearAppChanged(app) => {
+                        if let Some(form) = &mut state.clearing {
+                            form.app = app;
+                        }
 
-                            task.update(task_message);
+                        Command::none()
+                    }
+                    Message::ClearSecureToggled(secure) => {
+                        if let Some(form) = &mut state.clearing {
+                            form.secure = secure;
+                        }
 
-                            if should_focus {
-                                let id = ClipboardItemUI::text_input_id(i);
-                                Command::batch(vec![
-                                    text_input::focus(id.clone()),
-                                    text_input::select_all(id),
-                                ])
-                            } else {
+                        Command::none()
+                    }
+                    // Lo borrado sale de la lista con los eventos del daemon o al releer.
+                    Message::ClearConfirmed => {
+                        if let Some(form) = state.clearing.take() {
+                            state.pending.push_back(Change::Clear(form.scope(), form.secure));
+                        }
+
+                        Command::none()
+                    }
+                    Message::SnippetPressed(i) => match state.snippets.get(i) {
+                        Some(snippet) => match Template::parse(&snippet.body) {
+                            Ok(template) if template.inputs().is_empty() => {
+                                Command::perform(copy_snippet(snippet.clone(), HashMap::new()), Message::Copied)
+                            }
+                            Ok(template) => {
+                                let answers = template.inputs().into_iter().map(|label| (label.to_string(), String::new())).collect();
+                                state.prompt = Some(SnippetPrompt {
+                                    snippet: snippet.clone(),
+                                    answers,
+                                });
+                                text_input::focus(snippet_input_id(0))
+                            }
+                            Err(err) => {
+                                state.error = Some(err);
                                 Command::none()
                             }
-                        } else {
-                            Command::none()
+                        },
+                        None => Command::none(),
+                    },
+                    Message::SnippetAnswered(i, value) => {
+                        if let Some((_, answer)) = state.prompt.as_mut().and_then(|prompt| prompt.answers.get_mut(i)) {
+                            *answer = value;
                         }
+
+                        Command::none()
                     }
-                    Message::Saved(_result) => {
-                        state.saving = false;
-                        saved = true;
+                    Message::SnippetNext(i) => text_input::focus(snippet_input_id(i)),
+                    Message::SnippetCopy => match state.prompt.take() {
+                        Some(prompt) => {
+                            Command::perform(copy_snippet(prompt.snippet, prompt.answers.into_iter().collect()), Message::Copied)
+                        }
+                        None => Command::none(),
+                    },
+                    Message::SnippetCancel => {
+                        state.prompt = None;
 
                         Command::none()
                     }
-                    Message::TabPressed { shift } => {
-                        if shift {
-                            widget::focus_previous()
-                        } else {
-                            widget::focus_next()
+                    Message::Undo => state.undo(),
+                    Message::UndoExpired(serial) => {
+                        if serial == state.undo_serial {
+                            state.undo = None;
                         }
+
+                        Command::none()
                     }
-                    Message::ToggleFullscreen(mode) => window::get_latest()
-                        .and_then(move |window| {
-                            window::change_mode(window, mode)
-                        }),
-                    Message::Loaded(_) => Command::none(),
-                };
+                    Message::Saved(result) => {
+                        state.saving = false;
 
-                if !saved {
-                    state.dirty = true;
-                }
+                        match result {
+                            Ok(None) => Command::none(),
+                            Ok(Some(undo)) => {
+                                state.undo = Some(undo);
+                                state.undo_serial += 1;
+                                let serial = state.undo_serial;
+                                Command::perform(async_std::task::sleep(UNDO_TIMEOUT), move |_| Message::UndoExpired(serial))
+                            }
+                            // Lo que se ve ya no coincide con el historial
+                            Err(err) => {
+                                state.error = Some(err.to_string());
+                                state.reload(true)
+                            }
+                        }
+                    }
+                    Message::History(HistoryChange::Event(event)) => {
+                        state.apply(event);
 
-                let save = if state.dirty && !state.saving {
-                    state.dirty = false;
-                    state.saving = true;
+                        Command::none()
+                    }
+                    Message::History(HistoryChange::Reload) => state.reload(true),
+                    Message::ThumbnailLoaded(id, thumbnail) => {
+                        state.thumbnails.insert(id, thumbnail);
 
-                    Command::perform(
-                        SavedState {
-                            input_value: state.input_value.clone(),
-                            filter: state.filter,
-                            items: state.tasks.clone(),
+                        Command::none()
+                    }
+                    Message::PreviewLoaded(id, handle) => {
+                        if let Some((preview, full)) = &mut state.preview {
+                            if *preview == id {
+                                *full = handle;
+                            }
                         }
-                            .save(),
-                        Message::Saved,
-                    )
-                } else {
-                    Command::none()
+
+                        Command::none()
+                    }
+                    Message::FocusChanged(focused) => state.context.focus_changed(focused),
+                    Message::KeyPressed { key, modifiers, text, status } => {
+                        let editing = state.prompt.is_some()
+                            || state.clearing.is_some()
+                            || state
+                                .items
+                                .iter()
+                                .any(|item| !matches!(item.state, ClipboardItemState::Idle));
+                        match state.context.keys.action(&key, modifiers) {
+                            // Mientras se edita un elemento las teclas son del campo de texto.
+                            Some(_) if editing && status == event::Status::Captured => Command::none(),
+                            Some(action) => state.key_action(action),
+                            // Lo que no es un atajo se escribe en la búsqueda aunque no tenga el foco.
+                            None if status == event::Status::Ignored && !editing => {
+                                state.type_to_search(&key, modifiers, text.as_deref())
+                            }
+                            None => Command::none(),
+                        }
+                    }
+                    Message::Loaded(generation, Ok(saved)) if generation == state.generation => {
+                        state.items = saved.items;
+                        state.tags = saved.tags;
+                        state.snippets = saved.snippets;
+                        state.status = saved.status;
+                        state.complete = saved.complete;
+
+                        Command::none()
+                    }
+                    Message::MoreLoaded(generation, Ok(saved)) if generation == state.generation => {
+                        state.loading_more = false;
+                        state.complete = saved.complete;
+                        // Lo agregado mientras tanto puede estar ya en la lista.
+                        for item in saved.items {
+                            if !state.items.iter().any(|shown| shown.item.id == item.item.id) {
+                                state.items.push(item);
+                            }
+                        }
+
+                        Command::none()
+                    }
+                    Message::Loaded(generation, Err(err)) | Message::MoreLoaded(generation, Err(err))
+                        if generation == state.generation =>
+                    {
+                        state.loading_more = false;
+                        state.error = Some(err.to_string());
+
+                        Command::none()
+                    }
+                    // Respuestas de una búsqueda anterior.
+                    Message::Loaded(..) | Message::MoreLoaded(..) => Command::none(),
                 };
 
-                Command::batch(vec![command, save])
+                let save = state.next_save();
+                let images = state.load_images();
+
+                Command::batch(vec![command, save, images])
             }
         }
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         match self {
-            MainWindows::Loading => loading_message(),
-            MainWindows::Loaded(State {
-                                    input_value,
-                                    filter,
-                                    tasks,
-                                    ..
-                                }) => {
-                let input = text_input("What needs to be done?", input_value)
-                    .id("new-task")
-                    .on_input(Message::InputChanged)
-                    .on_submit(Message::CreateTask)
-                    .padding(15)
-                    .size(30)
-                    .align_x(Center);
-
-                let controls = view_controls(tasks, *filter);
-                let filtered_tasks =
-                    tasks.iter().filter(|task| filter.matches(task));
-
-                let tasks: Element<_> = if filtered_tasks.count() > 0 {
-                    keyed_column(
-                        tasks
-                            .iter()
-                            .enumerate()
-                            .filter(|(_, task)| filter.matches(task))
-                            .map(|(i, task)| {
-                                (
-                                    task.id,
-                                    task.view(i).map(move |message| {
-                                        Message::TaskMessage(i, message)
-                                    }),
-                                )
-                            }),
-                    )
-                        .spacing(10)
+            MainWindows::Loading(_) => loading_message(),
+            MainWindows::Loaded(state) => {
+                let header = view_header(&state.status, &state.error, state.clearing.as_ref());
+
+                let input = text_input("Search the clipboard history...", &state.query)
+                    .id(SEARCH_INPUT)
+                    .on_input(Message::QueryChanged)
+                    .padding(12)
+                    .size(18);
+
+                let visible = state.visible();
+                let selected = state.selected_index();
+
+                let controls = view_controls(visible.len(), state.filter, state.kind);
+                let tags = view_tags(&state.tags, state.tag.as_deref());
+
+                let items: Element<_> = if !visible.is_empty() {
+                    let now = now();
+                    keyed_column(visible.into_iter().enumerate().map(|(position, i)| {
+                        let item = &state.items[i];
+                        (
+                            item.item.id,
+                            item.view(
+                                i,
+                                position,
+                                now,
+                                selected == Some(i),
+                                thumbnail(state, item),
+                                &state.context.presets,
+                            )
+                                .map(move |message| Message::Item(i, message)),
+                        )
+                    }))
+                        .spacing(4)
                         .into()
+                } else if !state.query.is_empty() || state.kind.is_some() || state.tag.is_some() {
+                    empty_message("Nothing matches your search")
                 } else {
-                    empty_message(match filter {
-                        Filter::All => "You have not created a task yet...",
-                        Filter::Active => "All your tasks are done! :D",
-                        Filter::Completed => {
-                            "You have not completed a task yet..."
-                        }
+                    empty_message(match state.filter {
+                        Filter::All => "Nothing copied yet...",
+                        Filter::Favorites => "Star an item to keep it here",
+                        Filter::Text => "No text copied yet...",
+                        Filter::Images => "No images copied yet...",
+                        Filter::Files => "No files copied yet...",
                     })
                 };
 
-                let content = column![input, controls, tasks]
-                    .spacing(20)
-                    .max_width(800);
+                let snippets = match &state.prompt {
+                    Some(prompt) => Some(view_prompt(prompt)),
+                    None => view_snippets(&state.snippets),
+                };
 
-                scrollable(container(content).center_x(Fill).padding(40)).into()
+                let mut content = column![header]
+                    .push_maybe(state.clearing.as_ref().map(view_clear))
+                    .push(input)
+                    .push(controls)
+                    .push_maybe(tags)
+                    .push_maybe(snippets)
+                    .push(
+                    scrollable(items)
+                        .id(items_scrollable())
+                        .on_scroll(Message::Scrolled)
+                        .height(FillPortion(3)),
+                    )
+                    .spacing(12);
+
+                if let Some(i) = selected.filter(|_| state.show_preview) {
+                    let item = &state.items[i];
+                    let full = state
+                        .preview
+                        .as_ref()
+                        .filter(|(id, _)| *id == item.item.id)
+                        .and_then(|(_, full)| full.as_ref());
+                    content = content.push(view_preview(&item.item, thumbnail(state, item), full));
+                }
+
+                if let Some(undo) = &state.undo {
+                    content = content.push(view_undo(undo));
+                }
+
+                container(content).padding(20).into()
             }
         }
     }
 
+    // Los atajos se resuelven en `update` según la configuración. También llegan
+    // las teclas que captura el campo de búsqueda, como Enter o Escape.
     fn subscription(&self) -> Subscription<Message> {
-        use keyboard::key;
-
-        keyboard::on_key_press(|key, modifiers| {
-            let keyboard::Key::Named(key) = key else {
-                return None;
-            };
-
-            match (key, modifiers) {
-                (key::Named::Tab, _) => Some(Message::TabPressed {
-                    shift: modifiers.shift(),
-                }),
-                (key::Named::ArrowUp, keyboard::Modifiers::SHIFT) => {
-                    Some(Message::ToggleFullscreen(window::Mode::Fullscreen))
-                }
-                (key::Named::ArrowDown, keyboard::Modifiers::SHIFT) => {
-                    Some(Message::ToggleFullscreen(window::Mode::Windowed))
+        Subscription::batch([
+            Subscription::run(history_changes),
+            event::listen_with(|event, status, _| match event {
+                iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, text, .. }) => {
+                    let text = text.map(|text| text.to_string());
+                    Some(Message::KeyPressed { key, modifiers, text, status })
                 }
+                iced::Event::Window(window::Event::Focused) => Some(Message::FocusChanged(true)),
+                iced::Event::Window(window::Event::Unfocused) => Some(Message::FocusChanged(false)),
                 _ => None,
+            }),
+        ])
+    }
+}
+
+// `client::watch` bloquea, así que corre en su propio hilo.
+fn history_changes() -> impl Stream<Item = Message> {
+    stream::channel(100, |mut output| async move {
+        let (sender, mut receiver) = mpsc::unbounded();
+        thread::spawn(move || client::watch(|change| sender.unbounded_send(change).is_ok()));
+        while let Some(change) = receiver.next().await {
+            if output.send(Message::History(change)).await.is_err() {
+                break;
             }
-        })
+        }
+    })
+}
+
+fn thumbnail<'a>(state: &'a State, item: &ClipboardItemUI) -> Option<&'a Thumbnail> {
+    state.thumbnails.get(&item.item.id)?.as_ref()
+}
+
+fn items_scrollable() -> scrollable::Id {
+    scrollable::Id::new("items")
+}
+
+// El daemon es quien mantiene el contenido en el portapapeles.
+async fn copy(id: i32, plain: bool) -> Result<(), String> {
+    client::set_clipboard(id, plain).map_err(|err| format!("Cannot copy item {id}: {err}"))
+}
+
+// Los resultados de texto llegan como un elemento nuevo, por el daemon o al releer.
+async fn run_action(id: i32, action: &'static Action) -> Result<(), String> {
+    client::action(id, action.name)
+        .map(|_| ())
+        .map_err(|err| format!("{} failed: {err}", action.label))
+}
+
+// Codificar una imagen grande lleva tiempo, así que se hace fuera del hilo de la interfaz.
+async fn save_item(item: ClipboardItem, path: PathBuf) -> Result<Vec<PathBuf>, String> {
+    async_std::task::spawn_blocking(move || {
+        save::save(&item, Some(&path), None).map_err(|err| format!("Cannot save item {}: {err}", item.id))
+    })
+    .await
+}
+
+async fn copy_snippet(snippet: Snippet, inputs: HashMap<String, String>) -> Result<(), String> {
+    client::expand_snippet(&snippet, inputs)
+        .and_then(|expanded| client::copy_text(&expanded))
+        .map(|_| ())
+        .map_err(|err| format!("Cannot copy snippet {}: {err}", snippet.name))
+}
+
+async fn copy_transformed(id: i32, pipeline: Pipeline) -> Result<(), String> {
+    client::copy_transformed(id, &pipeline)
+        .map(|_| ())
+        .map_err(|err| format!("Cannot transform item {id}: {err}"))
+}
+
+// Opciones del menú de cada fila: las acciones de su clase y, para el texto, las
+// transformaciones y los presets de la configuración.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEntry {
+    Action(&'static Action),
+    Transform(String),
+    SaveAs,
+    AddTag,
+    RemoveTag(String),
+}
+
+impl fmt::Display for MenuEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MenuEntry::Action(action) => write!(f, "{action}"),
+            MenuEntry::Transform(name) => write!(f, "Copy as {name}"),
+            MenuEntry::SaveAs => f.write_str("Save as…"),
+            MenuEntry::AddTag => f.write_str("Add tag…"),
+            MenuEntry::RemoveTag(tag) => write!(f, "Remove tag {tag}"),
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 struct ClipboardItemUI {
-    id: i32,
-    content: String,
-    completed: bool,
-    is_focused: Option<Focus>,
-    #[serde(skip)]
+    item: ClipboardItem,
     state: ClipboardItemState,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum ClipboardItemState {
+    #[default]
     Idle,
     Editing,
-}
-
-impl Default for ClipboardItemState {
-    fn default() -> Self {
-        Self::Idle
-    }
+    // Eligiendo dónde guardar el elemento.
+    Saving(String),
+    // Escribiendo el nombre de una etiqueta nueva.
+    Tagging(String),
 }
 
 #[derive(Debug, Clone)]
 pub enum ClipboardItemMessage {
-    Completed(bool),
+    Copy,
+    ToggleFavorite,
     Edit,
-    DescriptionEdited(String),
+    ContentEdited(String),
     FinishEdition,
     Delete,
+    Menu(MenuEntry),
+    SavePathChanged(String),
+    SaveFormat(ImageFormat),
+    Save,
+    Cancel,
+    TagChanged(String),
+    AddTag,
+    // Muestra solo los elementos con esa etiqueta.
+    ShowTag(String),
 }
 
 impl ClipboardItemUI {
     fn text_input_id(i: usize) -> text_input::Id {
-        text_input::Id::new(format!("task-{i}"))
+        text_input::Id::new(format!("item-{i}"))
     }
 
-    fn new(description: String) -> Self {
+    fn new(item: ClipboardItem) -> Self {
         ClipboardItemUI {
-            id: 1,
-            content: description,
-            completed: false,
+            item,
             state: ClipboardItemState::Idle,
-            is_focused: None,
         }
     }
 
-    fn update(&mut self, message: ClipboardItemMessage) {
+    // `query` ya está en minúsculas. Los secretos no se buscan por su contenido.
+    fn matches(&self, query: &str) -> bool {
+        let item = &self.item;
+        query.is_empty()
+            || (!item.secret && item.content.to_lowercase().contains(query))
+            || item
+                .source_app
+                .as_ref()
+                .is_some_and(|app| app.to_lowercase().contains(query))
+    }
+
+    fn has_tag(&self, tag: &str) -> bool {
+        self.item.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag))
+    }
+
+    // Solo se puede editar el texto visible.
+    fn editable(&self) -> bool {
+        self.item.content_type == "text" && !self.item.secret
+    }
+
+    // Devuelve el cambio que hay que guardar, si lo hay.
+    fn update(&mut self, message: ClipboardItemMessage) -> Option<Change> {
         match message {
-            ClipboardItemMessage::Completed(completed) => {
-                self.completed = completed;
+            ClipboardItemMessage::ToggleFavorite => {
+                self.item.favorite = !self.item.favorite;
+                return Some(Change::Pin(self.item.id, self.item.favorite));
             }
             ClipboardItemMessage::Edit => {
-                self.state = ClipboardItemState::Editing;
+                if self.editable() {
+                    self.state = ClipboardItemState::Editing;
+                }
             }
-            ClipboardItemMessage::DescriptionEdited(new_description) => {
-                self.content = new_description;
+            ClipboardItemMessage::ContentEdited(content) => {
+                self.item.content = content;
             }
             ClipboardItemMessage::FinishEdition => {
-                if !self.content.is_empty() {
+                if !self.item.content.is_empty() {
                     self.state = ClipboardItemState::Idle;
+                    return Some(Change::Edit(self.item.id, self.item.content.clone()));
+                }
+            }
+            ClipboardItemMessage::SavePathChanged(path) => {
+                if let ClipboardItemState::Saving(saving) = &mut self.state {
+                    *saving = path;
+                }
+            }
+            // El formato de las imágenes sale de la extensión.
+            ClipboardItemMessage::SaveFormat(format) => {
+                if let ClipboardItemState::Saving(saving) = &mut self.state {
+                    *saving = Path::new(saving).with_extension(format.extension()).display().to_string();
+                }
+            }
+            ClipboardItemMessage::Cancel => {
+                self.state = ClipboardItemState::Idle;
+            }
+            ClipboardItemMessage::TagChanged(tag) => {
+                if let ClipboardItemState::Tagging(tagging) = &mut self.state {
+                    *tagging = tag;
                 }
             }
-            ClipboardItemMessage::Delete => {}
+            ClipboardItemMessage::Copy
+            | ClipboardItemMessage::Delete
+            | ClipboardItemMessage::Menu(_)
+            | ClipboardItemMessage::Save
+            | ClipboardItemMessage::AddTag
+            | ClipboardItemMessage::ShowTag(_) => {}
         }
+        None
     }
 
-    fn view(&self, i: usize) -> Element<ClipboardItemMessage> {
+    // `position` es el lugar entre los visibles: los nueve primeros tienen atajo.
+    fn view<'a>(
+        &'a self,
+        i: usize,
+        position: usize,
+        now: u64,
+        selected: bool,
+        thumbnail: Option<&'a Thumbnail>,
+        presets: &BTreeMap<String, Vec<String>>,
+    ) -> Element<'a, ClipboardItemMessage> {
         match &self.state {
             ClipboardItemState::Idle => {
-                let content = if self.content.len() > 30 {
-                    format!("{} ...", &self.content[..30])
+                let slot = if position < 9 {
+                    format!("{}  ·  ", position + 1)
                 } else {
-                    self.content.clone()
+                    String::new()
                 };
+                let preview = Preview::new(&self.item, PREVIEW_WIDTH);
+                let lines = match preview.lines {
+                    1 => String::new(),
+                    lines if self.item.content_type == "files" => format!("  ·  {lines} files"),
+                    lines => format!("  ·  {lines} lines"),
+                };
+                let details = text!(
+                    "{slot}{}{lines}  ·  {}",
+                    kind_label(&self.item),
+                    relative_time(&self.item.created_at, now)
+                )
+                    .size(12)
+                    .color([0.5, 0.5, 0.5]);
 
-                let checkbox = checkbox(content, self.completed)
-                    .on_toggle(ClipboardItemMessage::Completed)
+                let summary = match thumbnail {
+                    Some(thumbnail) => text!(
+                        "{} × {} px  ·  {}",
+                        thumbnail.width,
+                        thumbnail.height,
+                        images::format_size(thumbnail.size)
+                    ),
+                    None => text(preview.text),
+                };
+                let chips = (!self.item.tags.is_empty()).then(|| {
+                    row(self.item.tags.iter().map(|tag| {
+                        button(text!("#{tag}").size(12))
+                            .on_press(ClipboardItemMessage::ShowTag(tag.clone()))
+                            .padding([1, 6])
+                            .style(button::secondary)
+                            .into()
+                    }))
+                        .spacing(4)
+                });
+                let summary = column![
+                    details,
+                    summary.size(15).shaping(text::Shaping::Advanced),
+                ]
+                    .push_maybe(chips)
+                    .spacing(2);
+                let summary = match thumbnail {
+                    Some(thumbnail) => row![
+                        image(thumbnail.handle.clone()).height(48).width(64).content_fit(ContentFit::Contain),
+                        summary
+                    ]
+                        .spacing(8)
+                        .align_y(Center)
+                        .into(),
+                    None => Element::from(summary),
+                };
+
+                let summary = button(summary)
+                    .on_press(ClipboardItemMessage::Copy)
                     .width(Fill)
-                    .size(17)
-                    .text_shaping(text::Shaping::Advanced);
+                    .padding(8)
+                    .style(button::text);
 
-                row![
-                    checkbox,
-                    button(edit_icon())
-                        .on_press(ClipboardItemMessage::Edit)
-                        .padding(10)
-                        .style(button::text),
-                ]
-                    .spacing(20)
+                let star = button(
+                    text(if self.item.favorite { "★" } else { "☆" })
+                        .shaping(text::Shaping::Advanced)
+                        .width(20)
+                        .align_x(Center),
+                )
+                    .on_press(ClipboardItemMessage::ToggleFavorite)
+                    .padding(8)
+                    .style(button::text);
+
+                let edit = button(edit_icon())
+                    .on_press_maybe(self.editable().then_some(ClipboardItemMessage::Edit))
+                    .padding(8)
+                    .style(button::text);
+
+                let delete = button(delete_icon())
+                    .on_press(ClipboardItemMessage::Delete)
+                    .padding(8)
+                    .style(button::text);
+
+                let mut menu: Vec<_> = actions::available(&self.item).into_iter().map(MenuEntry::Action).collect();
+                if !self.item.secret {
+                    menu.push(MenuEntry::SaveAs);
+                }
+                menu.push(MenuEntry::AddTag);
+                menu.extend(self.item.tags.iter().cloned().map(MenuEntry::RemoveTag));
+                if self.item.content_type != "image" && !self.item.secret {
+                    let transforms = Transform::ALL.iter().map(|transform| transform.name().to_string());
+                    menu.extend(transforms.chain(presets.keys().cloned()).map(MenuEntry::Transform));
+                }
+                let menu = (!menu.is_empty()).then(|| {
+                    pick_list(menu, None::<MenuEntry>, ClipboardItemMessage::Menu)
+                        .placeholder("Actions")
+                        .text_size(13)
+                        .padding(6)
+                });
+
+                let row = row![summary]
+                    .push_maybe(menu)
+                    .push(star)
+                    .push(edit)
+                    .push(delete)
+                    .spacing(4)
+                    .align_y(Center);
+
+                if selected {
+                    container(row).style(container::rounded_box).into()
+                } else {
+                    row.into()
+                }
+            }
+            ClipboardItemState::Saving(path) => {
+                let path_input = text_input("Save to...", path)
+                    .id(Self::text_input_id(i))
+                    .on_input(ClipboardItemMessage::SavePathChanged)
+                    .on_submit(ClipboardItemMessage::Save)
+                    .padding(10);
+                let format = (self.item.content_type == "image").then(|| {
+                    let current = ImageFormat::ALL
+                        .into_iter()
+                        .find(|format| Path::new(path).extension().is_some_and(|extension| extension == format.extension()));
+                    pick_list(ImageFormat::ALL, current, ClipboardItemMessage::SaveFormat).padding(10)
+                });
+
+                row![path_input]
+                    .push_maybe(format)
+                    .push(button("Save").on_press(ClipboardItemMessage::Save).padding(10))
+                    .push(
+                        button("Cancel")
+                            .on_press(ClipboardItemMessage::Cancel)
+                            .padding(10)
+                            .style(button::text),
+                    )
+                    .spacing(10)
                     .align_y(Center)
                     .into()
             }
+            ClipboardItemState::Tagging(tag) => row![
+                text_input("Tag name, like sql or customer emails", tag)
+                    .id(Self::text_input_id(i))
+                    .on_input(ClipboardItemMessage::TagChanged)
+                    .on_submit(ClipboardItemMessage::AddTag)
+                    .padding(10),
+                button("Add tag").on_press(ClipboardItemMessage::AddTag).padding(10),
+                button("Cancel")
+                    .on_press(ClipboardItemMessage::Cancel)
+                    .padding(10)
+                    .style(button::text),
+            ]
+                .spacing(10)
+                .align_y(Center)
+                .into(),
             ClipboardItemState::Editing => {
-                let text_input =
-                    text_input("Describe your task...", &self.content)
-                        .id(Self::text_input_id(i))
-                        .on_input(ClipboardItemMessage::DescriptionEdited)
-                        .on_submit(ClipboardItemMessage::FinishEdition)
-                        .padding(10);
+                let text_input = text_input("Clipboard content...", &self.item.content)
+                    .id(Self::text_input_id(i))
+                    .on_input(ClipboardItemMessage::ContentEdited)
+                    .on_submit(ClipboardItemMessage::FinishEdition)
+                    .padding(10);
 
                 row![
                     text_input,
@@ -372,46 +1448,219 @@ impl ClipboardItemUI {
     }
 }
 
-impl operation::Focusable for ClipboardItemState {
-    fn is_focused(&self) -> bool {
-        ClipboardItemState::is_focused(self)
+// Panel con el contenido completo del elemento elegido.
+fn view_preview<'a>(
+    item: &'a ClipboardItem,
+    thumbnail: Option<&Thumbnail>,
+    full: Option<&image::Handle>,
+) -> Element<'a, Message> {
+    let gray = [0.5, 0.5, 0.5];
+    let info = match thumbnail {
+        Some(thumbnail) => format!(
+            "{}  ·  {} × {} px  ·  {}",
+            item.mime,
+            thumbnail.width,
+            thumbnail.height,
+            images::format_size(thumbnail.size)
+        ),
+        None if item.content_type == "image" => item.mime.clone(),
+        None => {
+            let lines = item.content.lines().count().max(1);
+            format!(
+                "{lines} {}  ·  {} characters",
+                if lines == 1 { "line" } else { "lines" },
+                item.content.chars().count()
+            )
+        }
+    };
+
+    let body: Element<_> = match item.content_type.as_str() {
+        _ if item.secret => text("The content of secrets is not shown").color(gray).into(),
+        "image" => match full {
+            Some(handle) => image(handle.clone())
+                .content_fit(ContentFit::Contain)
+                .width(Fill)
+                .height(Fill)
+                .into(),
+            None => text("Loading image...").color(gray).into(),
+        },
+        kind => {
+            let font = if kind == "files" || matches!(item.kind, Some(Kind::Code | Kind::Json | Kind::Xml)) {
+                Font::MONOSPACE
+            } else {
+                Font::DEFAULT
+            };
+            scrollable(
+                text(&item.content)
+                    .font(font)
+                    .size(14)
+                    .shaping(text::Shaping::Advanced)
+                    .width(Fill),
+            )
+                .height(Fill)
+                .into()
+        }
+    };
+
+    container(column![text(info).size(12).color(gray), body].spacing(6))
+        .padding(8)
+        .width(Fill)
+        .height(FillPortion(2))
+        .style(container::bordered_box)
+        .into()
+}
+
+fn kind_label(item: &ClipboardItem) -> String {
+    match (item.content_type.as_str(), item.kind) {
+        _ if item.secret => "🔒 Secret".to_string(),
+        ("image", _) => "🖼 Image".to_string(),
+        ("files", _) => "📁 Files".to_string(),
+        (_, Some(kind)) => match &item.language {
+            Some(language) => format!("{} {kind} · {language}", kind.icon()),
+            None => format!("{} {kind}", kind.icon()),
+        },
+        _ => "Text".to_string(),
     }
+}
+
+// Ancho de la vista previa de una fila, en columnas.
+const PREVIEW_WIDTH: usize = 80;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
 
-    fn focus(&mut self) {
-        ClipboardItemState::focus(self);
+// `created_at` viene de `datetime('now')` de SQLite: "AAAA-MM-DD HH:MM:SS" en UTC.
+fn relative_time(created_at: &str, now: u64) -> String {
+    let Some(then) = parse_timestamp(created_at) else {
+        return created_at.to_string();
+    };
+    let elapsed = now.saturating_sub(then);
+    match elapsed {
+        0..60 => "just now".to_string(),
+        60..3_600 => format!("{} min ago", elapsed / 60),
+        3_600..86_400 => format!("{} h ago", elapsed / 3_600),
+        86_400..172_800 => "yesterday".to_string(),
+        172_800..604_800 => format!("{} days ago", elapsed / 86_400),
+        _ => created_at.get(..10).unwrap_or(created_at).to_string(),
     }
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.split_once(' ')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    // Días desde 1970-01-01 en el calendario gregoriano (algoritmo de Howard Hinnant).
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    u64::try_from(days * 86_400 + hour * 3_600 + minute * 60 + second).ok()
+}
 
-    fn unfocus(&mut self) {
-        ClipboardItemState::unfocus(self);
+fn view_header<'a>(
+    status: &Option<Status>,
+    error: &'a Option<String>,
+    clearing: Option<&ClearForm>,
+) -> Element<'a, Message> {
+    let capture = match status {
+        Some(status) => format!("Capture {}", status.capture_state()),
+        None => "Daemon not running, history is read-only".to_string(),
+    };
+    let color = match status {
+        Some(status) if !status.paused && !status.incognito => [0.5, 0.5, 0.5],
+        _ => [0.9, 0.5, 0.1],
+    };
+
+    let clear = button(text("Clear history…").size(14))
+        .on_press(Message::ClearPressed)
+        .padding(6)
+        .style(if clearing.is_some() { button::secondary } else { button::text });
+
+    let header = column![
+        row![text(capture).size(14).color(color).width(Fill), clear].align_y(Center)
+    ];
+    match error {
+        Some(error) => header.push(text(error).size(14).color([0.9, 0.2, 0.2])).into(),
+        None => header.into(),
     }
 }
 
-// impl Focusable for ClipboardItemUI {
-//     fn is_focused(&self) -> bool;
-//     fn focus(&mut self);
-//     fn unfocus(&mut self);
-// }
-//
-// impl<Highlighter: text::Highlighter> operation::Focusable for State<Highlighter>
-// {
-//     fn is_focused(&self) -> bool {
-//         self.focus.is_some()
-//     }
-//
-//     fn focus(&mut self) {
-//         self.focus = Some(Focus::now());
-//     }
-//
-//     fn unfocus(&mut self) {
-//         self.focus = None;
-//     }
-// }
+// Qué borrar del historial, y la confirmación.
+fn view_clear(form: &ClearForm) -> Element<'_, Message> {
+    let scope = row![
+        pick_list(ClearPreset::ALL, Some(form.preset), Message::ClearPresetChanged)
+            .text_size(14)
+            .padding(6),
+        text_input("From any app", &form.app)
+            .on_input(Message::ClearAppChanged)
+            .on_submit(Message::ClearConfirmed)
+            .size(14)
+            .padding(6),
+    ]
+        .spacing(10)
+        .align_y(Center);
+    let note = match form.secure {
+        true => "Entries are wiped from the database file and cannot be restored",
+        false => "Entries go to the trash and can be restored",
+    };
+    let buttons = row![
+        checkbox("Secure wipe", form.secure)
+            .on_toggle(Message::ClearSecureToggled)
+            .text_size(14)
+            .width(Fill),
+        button(text(if form.secure { "Wipe" } else { "Clear" }).size(14))
+            .on_press(Message::ClearConfirmed)
+            .padding(6)
+            .style(button::danger),
+        button(text("Cancel").size(14))
+            .on_press(Message::ClearPressed)
+            .padding(6)
+            .style(button::text),
+    ]
+        .spacing(10)
+        .align_y(Center);
+
+    container(column![scope, text(note).size(12).color([0.5, 0.5, 0.5]), buttons].spacing(8))
+        .padding(8)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+}
 
-fn view_controls(tasks: &[ClipboardItemUI], current_filter: Filter) -> Element<Message> {
-    let tasks_left = tasks.iter().filter(|task| !task.completed).count();
+// Aviso con el botón para deshacer el último borrado.
+fn view_undo(undo: &Undo) -> Element<'_, Message> {
+    let count = undo.ids.len();
+    let message = match (undo.cleared, count) {
+        (true, _) => format!("Cleared, {count} items moved to the trash"),
+        (false, 1) => "Item moved to the trash".to_string(),
+        (false, _) => format!("{count} items moved to the trash"),
+    };
+    container(
+        row![
+            text(message).size(14).width(Fill),
+            button(text("Undo").size(14)).on_press(Message::Undo).padding(6),
+        ]
+            .spacing(10)
+            .align_y(Center),
+    )
+        .padding(8)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
 
+fn view_controls<'a>(count: usize, current_filter: Filter, kind: Option<Kind>) -> Element<'a, Message> {
     let filter_button = |label, filter, current_filter| {
-        let label = text(label);
+        let label = text(label).size(14);
 
         let button = button(label).style(if filter == current_filter {
             button::primary
@@ -419,43 +1668,172 @@ fn view_controls(tasks: &[ClipboardItemUI], current_filter: Filter) -> Element<M
             button::text
         });
 
-        button.on_press(Message::FilterChanged(filter)).padding(8)
+        button.on_press(Message::FilterChanged(filter)).padding(6)
     };
 
     row![
-        text!(
-            "{tasks_left} {} left",
-            if tasks_left == 1 { "task" } else { "tasks" }
-        )
-        .width(Fill),
         row![
             filter_button("All", Filter::All, current_filter),
-            filter_button("Active", Filter::Active, current_filter),
-            filter_button("Completed", Filter::Completed, current_filter,),
+            filter_button("Favorites", Filter::Favorites, current_filter),
+            filter_button("Text", Filter::Text, current_filter),
+            filter_button("Images", Filter::Images, current_filter),
+            filter_button("Files", Filter::Files, current_filter),
         ]
-        .spacing(10)
+        .spacing(4)
+        .width(Fill),
+        pick_list(KindChoice::ALL, Some(KindChoice(kind)), Message::KindChanged)
+            .text_size(14)
+            .padding(6),
+        text!("{count} {}", if count == 1 { "item" } else { "items" }).size(14),
     ]
-        .spacing(20)
+        .spacing(10)
         .align_y(Center)
         .into()
 }
 
-#[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
-)]
+// Barra de etiquetas: elegir una muestra sus elementos; volver a elegirla, todos.
+fn view_tags<'a>(tags: &'a [String], current: Option<&str>) -> Option<Element<'a, Message>> {
+    if tags.is_empty() && current.is_none() {
+        return None;
+    }
+    let chips = row(tags.iter().map(|tag| {
+        let selected = current.is_some_and(|current| current.eq_ignore_ascii_case(tag));
+        button(text!("#{tag}").size(13))
+            .on_press(Message::TagChanged((!selected).then(|| tag.clone())))
+            .padding([2, 8])
+            .style(if selected { button::primary } else { button::secondary })
+            .into()
+    }))
+        .spacing(6);
+    Some(
+        scrollable(chips)
+            .direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+            .into(),
+    )
+}
+
+// Los snippets que coinciden con la búsqueda; al pulsar uno se copia ya completado.
+fn view_snippets(snippets: &[Snippet]) -> Option<Element<'_, Message>> {
+    if snippets.is_empty() {
+        return None;
+    }
+    let rows = snippets.iter().enumerate().map(|(i, snippet)| {
+        let body = preview::truncate(&preview::normalize(&snippet.body), PREVIEW_WIDTH);
+        button(
+            column![
+                text!("✂ Snippet  ·  {}", snippet.name).size(12).color([0.5, 0.5, 0.5]),
+                text(body).size(15).shaping(text::Shaping::Advanced),
+            ]
+                .spacing(2),
+        )
+            .on_press(Message::SnippetPressed(i))
+            .width(Fill)
+            .padding(8)
+            .style(button::text)
+            .into()
+    });
+    Some(column(rows).spacing(4).into())
+}
+
+fn snippet_input_id(i: usize) -> text_input::Id {
+    text_input::Id::new(format!("snippet-{i}"))
+}
+
+// Las preguntas de un snippet antes de copiarlo.
+fn view_prompt(prompt: &SnippetPrompt) -> Element<'_, Message> {
+    let count = prompt.answers.len();
+    let inputs = prompt.answers.iter().enumerate().map(|(i, (label, answer))| {
+        // Enter pasa a la siguiente pregunta y, en la última, copia.
+        let submit = if i + 1 < count {
+            Message::SnippetNext(i + 1)
+        } else {
+            Message::SnippetCopy
+        };
+        text_input(label, answer)
+            .id(snippet_input_id(i))
+            .on_input(move |value| Message::SnippetAnswered(i, value))
+            .on_submit(submit)
+            .padding(10)
+            .into()
+    });
+    let buttons = row![
+        button("Copy").on_press(Message::SnippetCopy).padding(10),
+        button("Cancel")
+            .on_press(Message::SnippetCancel)
+            .padding(10)
+            .style(button::text),
+    ]
+        .spacing(10);
+
+    container(
+        column![text!("✂ {}", prompt.snippet.name).size(14)]
+            .extend(inputs)
+            .push(buttons)
+            .spacing(8),
+    )
+        .padding(8)
+        .width(Fill)
+        .style(container::bordered_box)
+        .into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Filter {
     #[default]
     All,
-    Active,
-    Completed,
+    Favorites,
+    Text,
+    Images,
+    Files,
 }
 
 impl Filter {
-    fn matches(self, task: &ClipboardItemUI) -> bool {
+    fn content_type(self) -> Option<&'static str> {
+        match self {
+            Filter::All | Filter::Favorites => None,
+            Filter::Text => Some("text"),
+            Filter::Images => Some("image"),
+            Filter::Files => Some("files"),
+        }
+    }
+
+    fn matches(self, item: &ClipboardItemUI) -> bool {
+        let item = &item.item;
         match self {
             Filter::All => true,
-            Filter::Active => !task.completed,
-            Filter::Completed => task.completed,
+            Filter::Favorites => item.favorite,
+            Filter::Text => item.content_type == "text",
+            Filter::Images => item.content_type == "image",
+            Filter::Files => item.content_type == "files",
+        }
+    }
+}
+
+// Filtro por clase de texto; `None` muestra todas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KindChoice(Option<Kind>);
+
+impl KindChoice {
+    const ALL: [KindChoice; 11] = [
+        KindChoice(None),
+        KindChoice(Some(Kind::Url)),
+        KindChoice(Some(Kind::Email)),
+        KindChoice(Some(Kind::Phone)),
+        KindChoice(Some(Kind::Path)),
+        KindChoice(Some(Kind::Color)),
+        KindChoice(Some(Kind::Number)),
+        KindChoice(Some(Kind::Json)),
+        KindChoice(Some(Kind::Xml)),
+        KindChoice(Some(Kind::Code)),
+        KindChoice(Some(Kind::Prose)),
+    ];
+}
+
+impl fmt::Display for KindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(kind) => write!(f, "{} {kind}", kind.icon()),
+            None => f.write_str("Any kind"),
         }
     }
 }
@@ -468,7 +1846,7 @@ fn empty_message(message: &str) -> Element<'_, Message> {
     center(
         text(message)
             .width(Fill)
-            .size(25)
+            .size(20)
             .align_x(Center)
             .color([0.7, 0.7, 0.7]),
     )
@@ -494,48 +1872,104 @@ fn delete_icon() -> Text<'static> {
     icon('\u{F1F8}')
 }
 
-// Persistence
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Una página del historial, con lo que se muestra alrededor.
+#[derive(Debug, Clone)]
 struct SavedState {
-    input_value: String,
-    filter: Filter,
     items: Vec<ClipboardItemUI>,
+    tags: Vec<String>,
+    // Solo con la primera página de una búsqueda.
+    snippets: Vec<Snippet>,
+    status: Option<Status>,
+    // La página vino incompleta: no hay más.
+    complete: bool,
+}
+
+#[derive(Debug, Clone)]
+struct LoadError(String);
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot load the history: {}", self.0)
+    }
 }
 
+// Una modificación del historial hecha desde la GUI.
 #[derive(Debug, Clone)]
-enum LoadError {
-    File,
-    Format,
+enum Change {
+    Delete(i32),
+    Edit(i32, String),
+    Pin(i32, bool),
+    Tag(i32, String, bool),
+    Clear(ClearScope, bool),
+    Restore(Vec<i32>),
+}
+
+impl Change {
+    fn id(&self) -> Option<i32> {
+        match self {
+            Change::Delete(id) | Change::Edit(id, _) | Change::Pin(id, _) | Change::Tag(id, ..) => Some(*id),
+            Change::Clear(..) | Change::Restore(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 enum SaveError {
-    File,
-    Write,
-    Format,
+    // El elemento ya no existe, por ejemplo porque se borró desde la CLI.
+    NotFound(i32),
+    Write(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::NotFound(id) => write!(f, "Item {id} no longer exists"),
+            SaveError::Write(err) => write!(f, "Cannot save the change: {err}"),
+        }
+    }
 }
 
 impl SavedState {
-    async fn load() -> Result<SavedState, LoadError> {
-        match get_clipboard_content() {
+    async fn load(query: PageQuery) -> Result<SavedState, LoadError> {
+        let terms = SearchTerms::parse(&query.search);
+        let snippets = match query.after.is_none() && terms.tags.is_empty() && !terms.text.trim().is_empty() {
+            true => db::search_snippets(terms.text.trim()).unwrap_or_default(),
+            false => Vec::new(),
+        };
+        match client::page(&query) {
             Ok(items) =>
                 Ok(SavedState {
-                    input_value: "".to_string(),
-                    filter: Filter::All,
-                    items: items.into_iter().map(|item| ClipboardItemUI {
-                        id: item.id,
-                        content: item.content,
-                        completed: false,
-                        state: ClipboardItemState::Idle,
-                        is_focused: None,
-                    }).collect(),
+                    complete: query.limit.is_none_or(|limit| items.len() < limit),
+                    tags: client::tags().unwrap_or_default().into_iter().map(|tag| tag.name).collect(),
+                    snippets,
+                    status: client::status(),
+                    items: items.into_iter().map(ClipboardItemUI::new).collect(),
                 }),
-            Err(_) => Err(LoadError::Format),
+            Err(err) => Err(LoadError(err.to_string())),
         }
     }
 
-    async fn save(self) -> Result<(), SaveError> {
-        // @todo: Implementar esta parte
-        Ok(())
+    // Cada cambio se envía por separado al daemon o, sin daemon, a la base de datos.
+    // Devuelve lo que se mandó a la papelera, para poder deshacerlo.
+    async fn save(change: Change) -> Result<Option<Undo>, SaveError> {
+        let result = match &change {
+            Change::Delete(id) => client::delete(*id).map(|_| {
+                Some(Undo {
+                    ids: vec![*id],
+                    cleared: false,
+                })
+            }),
+            Change::Edit(id, content) => client::edit(*id, content).map(|_| None),
+            Change::Pin(id, pinned) => client::pin(*id, *pinned).map(|_| None),
+            Change::Tag(id, tag, tagged) => client::tag(*id, tag, *tagged).map(|_| None),
+            // Lo borrado con `secure` no se puede recuperar.
+            Change::Clear(scope, secure) => client::clear(scope, *secure)
+                .map(|ids| (!secure && !ids.is_empty()).then_some(Undo { ids, cleared: true })),
+            Change::Restore(ids) => client::restore(ids).map(|_| None),
+        };
+        result.map_err(|err| match change.id() {
+            Some(id) if matches!(client::get(id), Ok(None)) => SaveError::NotFound(id),
+            _ => SaveError::Write(err.to_string()),
+        })
     }
-}
\ No newline at end of file
+}
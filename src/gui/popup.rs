@@ -0,0 +1,119 @@
+use std::env;
+
+use iced::window::settings::PlatformSpecific;
+use iced::window::{self, Level, Position};
+use iced::{Point, Size};
+use iced_layershell::actions::LayershellCustomActions;
+use iced_layershell::reexport::{Anchor, KeyboardInteractivity, Layer};
+use iced_layershell::settings::LayerShellSettings;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry;
+use wayland_client::{Connection as WaylandConnection, Dispatch, QueueHandle};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::ConnectionExt;
+
+use crate::config::PopupPosition;
+
+use super::Message;
+
+// En Wayland el popup es una superficie de wlr-layer-shell; en X11, o si el
+// compositor no la ofrece (GNOME), una ventana sin decoraciones y siempre
+// encima. El app-id (o el namespace de la capa) sirve para las reglas del compositor.
+pub const APP_ID: &str = "ropias-popup";
+
+const SIZE: Size = Size::new(450.0, 500.0);
+
+fn is_wayland() -> bool {
+    env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+// iced_layershell no devuelve un error si no puede crear la superficie, así
+// que antes se mira si el compositor anuncia wlr-layer-shell.
+pub fn has_layer_shell() -> bool {
+    if !is_wayland() {
+        return false;
+    }
+    let Ok(conn) = WaylandConnection::connect_to_env() else {
+        return false;
+    };
+    let Ok((globals, _)) = registry_queue_init::<Globals>(&conn) else {
+        return false;
+    };
+    globals
+        .contents()
+        .with_list(|list| list.iter().any(|global| global.interface == "zwlr_layer_shell_v1"))
+}
+
+struct Globals;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Globals {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+// Sin anclas el compositor la centra en la pantalla activa. La capa overlay
+// queda encima de paneles y pantallas completas, y el teclado es exclusivo
+// para que se pueda escribir sin hacer clic.
+pub fn layer_settings() -> LayerShellSettings {
+    LayerShellSettings {
+        anchor: Anchor::empty(),
+        layer: Layer::Overlay,
+        exclusive_zone: 0,
+        size: Some((SIZE.width as u32, SIZE.height as u32)),
+        keyboard_interactivity: KeyboardInteractivity::Exclusive,
+        ..LayerShellSettings::default()
+    }
+}
+
+// La aplicación no usa las acciones propias de iced_layershell (cambiar
+// anclas, tamaño...), así que ningún mensaje se convierte en una.
+impl TryFrom<Message> for LayershellCustomActions {
+    type Error = Message;
+
+    fn try_from(message: Message) -> Result<Self, Message> {
+        Err(message)
+    }
+}
+
+pub fn window_settings(position: PopupPosition) -> window::Settings {
+    let position = match position {
+        PopupPosition::Cursor => cursor_position().map_or(Position::Centered, Position::Specific),
+        PopupPosition::Center => Position::Centered,
+    };
+    window::Settings {
+        size: SIZE,
+        position,
+        resizable: false,
+        decorations: false,
+        level: Level::AlwaysOnTop,
+        platform_specific: PlatformSpecific {
+            application_id: APP_ID.to_string(),
+            ..PlatformSpecific::default()
+        },
+        ..window::Settings::default()
+    }
+}
+
+// Wayland no deja que un cliente conozca la posición global del puntero.
+fn cursor_position() -> Option<Point> {
+    if is_wayland() {
+        return None;
+    }
+    let (conn, screen) = x11rb::connect(None).ok()?;
+    let screen = &conn.setup().roots[screen];
+    let pointer = conn.query_pointer(screen.root).ok()?.reply().ok()?;
+    // Que el popup no quede fuera de la pantalla.
+    let max_x = (f32::from(screen.width_in_pixels) - SIZE.width).max(0.0);
+    let max_y = (f32::from(screen.height_in_pixels) - SIZE.height).max(0.0);
+    Some(Point::new(
+        f32::from(pointer.root_x).clamp(0.0, max_x),
+        f32::from(pointer.root_y).clamp(0.0, max_y),
+    ))
+}
@@ -0,0 +1,198 @@
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{ClearScope, ClipboardItem, PageQuery, Tag, TrashedItem};
+
+// Protocolo del socket de control: un mensaje JSON por línea, con la versión en cada mensaje.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub fn socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("ropias.sock"),
+        None => env::temp_dir().join(format!(
+            "ropias-{}.sock",
+            env::var("USER").unwrap_or_default()
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "kebab-case")]
+pub enum Request {
+    List { limit: Option<usize> },
+    Search { query: String, limit: Option<usize> },
+    Page { query: PageQuery },
+    Get { id: i32 },
+    // Con `plain` se copia solo el texto: las rutas de una lista de archivos, por ejemplo.
+    SetClipboard {
+        id: i32,
+        #[serde(default)]
+        plain: bool,
+    },
+    // Manda el elemento a la papelera.
+    Delete { id: i32 },
+    Pin { id: i32, pinned: bool },
+    Edit { id: i32, content: String },
+    Tag { id: i32, tag: String },
+    Untag { id: i32, tag: String },
+    // Responde con las etiquetas en uso.
+    Tags,
+    // Guarda un texto nuevo en el historial y lo copia. Responde con el elemento creado.
+    CopyText { content: String },
+    // Manda a la papelera lo que abarca `scope` (sin nada, todo) y responde con
+    // los ids, para poder deshacerlo. Con `secure` se borra para siempre y se
+    // limpia el archivo de la base de datos.
+    Clear {
+        #[serde(default)]
+        scope: ClearScope,
+        #[serde(default)]
+        secure: bool,
+    },
+    // Responde con la papelera.
+    Trash,
+    Restore { ids: Vec<i32> },
+    EmptyTrash,
+    // Sin `minutes` la pausa dura hasta recibir `Resume`.
+    Pause { minutes: Option<u64> },
+    Resume,
+    Incognito { enabled: bool },
+    Subscribe,
+    Status,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "kebab-case")]
+pub enum Response {
+    Items { items: Vec<ClipboardItem> },
+    Item { item: Option<ClipboardItem> },
+    Tags { tags: Vec<Tag> },
+    Trash { items: Vec<TrashedItem> },
+    // Los elementos que se mandaron a la papelera o se sacaron de ella.
+    Ids { ids: Vec<i32> },
+    Done,
+    Status { status: Status },
+    Event { event: Event },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub version: String,
+    pub protocol: u32,
+    pub paused: bool,
+    // Segundos desde UNIX_EPOCH en que termina una pausa temporal.
+    #[serde(default)]
+    pub paused_until: Option<u64>,
+    #[serde(default)]
+    pub incognito: bool,
+    pub items: usize,
+}
+
+impl Status {
+    // Descripción corta del estado de captura, para la CLI y la cabecera de la GUI.
+    pub fn capture_state(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        match (self.paused, self.paused_until, self.incognito) {
+            (true, Some(until), _) => {
+                format!("paused, resumes in {} min", until.saturating_sub(now).div_ceil(60))
+            }
+            (true, None, _) => "paused".to_string(),
+            (false, _, true) => "incognito, nothing is saved".to_string(),
+            (false, _, false) => "recording".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    Added { item: ClipboardItem },
+    Updated { item: ClipboardItem },
+    Removed { id: i32 },
+    Status { status: Status },
+}
+
+pub fn write_message<T: Serialize>(writer: &mut impl Write, body: T) -> io::Result<()> {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        body,
+    };
+    let mut line = serde_json::to_string(&envelope)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+// Devuelve None cuando el otro extremo cerró la conexión.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<Envelope<T>>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    // None si el daemon no está corriendo.
+    pub fn connect() -> Option<Client> {
+        let stream = UnixStream::connect(socket_path()).ok()?;
+        let writer = stream.try_clone().ok()?;
+        Some(Client {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    pub fn request(&mut self, request: Request) -> Result<Response, Box<dyn Error>> {
+        write_message(&mut self.writer, request)?;
+        match read_message::<Response>(&mut self.reader)? {
+            Some(envelope) if envelope.version != PROTOCOL_VERSION => Err(format!(
+                "daemon speaks protocol version {}, expected {PROTOCOL_VERSION}",
+                envelope.version
+            )
+            .into()),
+            Some(Envelope {
+                body: Response::Error { message },
+                ..
+            }) => Err(message.into()),
+            Some(envelope) => Ok(envelope.body),
+            None => Err("daemon closed the connection".into()),
+        }
+    }
+
+    // Tras `Request::Subscribe`, bloquea hasta el siguiente evento del daemon.
+    pub fn next_event(&mut self) -> Result<Option<Event>, Box<dyn Error>> {
+        loop {
+            match read_message::<Response>(&mut self.reader)? {
+                Some(Envelope {
+                    body: Response::Event { event },
+                    ..
+                }) => return Ok(Some(event)),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::sync::LazyLock;
+
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+// Qué parece ser un texto copiado. Se calcula al guardarlo y decide los filtros,
+// los iconos y las acciones que se ofrecen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Url,
+    Email,
+    Phone,
+    Path,
+    Color,
+    Number,
+    Json,
+    Xml,
+    Code,
+    Prose,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 10] = [
+        Kind::Url,
+        Kind::Email,
+        Kind::Phone,
+        Kind::Path,
+        Kind::Color,
+        Kind::Number,
+        Kind::Json,
+        Kind::Xml,
+        Kind::Code,
+        Kind::Prose,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Url => "url",
+            Kind::Email => "email",
+            Kind::Phone => "phone",
+            Kind::Path => "path",
+            Kind::Color => "color",
+            Kind::Number => "number",
+            Kind::Json => "json",
+            Kind::Xml => "xml",
+            Kind::Code => "code",
+            Kind::Prose => "prose",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Kind> {
+        Kind::ALL.into_iter().find(|known| known.as_str() == kind)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Url => "Link",
+            Kind::Email => "Email",
+            Kind::Phone => "Phone",
+            Kind::Path => "Path",
+            Kind::Color => "Color",
+            Kind::Number => "Number",
+            Kind::Json => "JSON",
+            Kind::Xml => "XML",
+            Kind::Code => "Code",
+            Kind::Prose => "Text",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Kind::Url => "🔗",
+            Kind::Email => "✉",
+            Kind::Phone => "☎",
+            Kind::Path => "📁",
+            Kind::Color => "🎨",
+            Kind::Number => "#",
+            Kind::Json => "{}",
+            Kind::Xml => "</>",
+            Kind::Code => "⌨",
+            Kind::Prose => "¶",
+        }
+    }
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Classification {
+    pub kind: Kind,
+    // Lenguaje probable, solo para código.
+    pub language: Option<&'static str>,
+}
+
+impl Classification {
+    fn of(kind: Kind) -> Self {
+        Classification { kind, language: None }
+    }
+}
+
+// Patrones de un solo valor: el texto entero tiene que coincidir.
+static URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i:(https?|ftp|file)://[^\s/$.?#][^\s]*|www\.[^\s.]+\.[^\s]{2,})$").unwrap()
+});
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i:mailto:)?[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}$").unwrap()
+});
+static PHONE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\+|00)?[0-9(][0-9 ().-]{5,}[0-9]$").unwrap());
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[+-]?(\d+|\d{1,3}([ ,.]\d{3})+)([.,]\d+)?([eE][+-]?\d+)?%?$|^0[xX][0-9a-fA-F]+$|^0[bB][01]+$")
+        .unwrap()
+});
+static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}\b").unwrap());
+static PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(~?/|\.\.?/|[A-Za-z]:\\)[^\n\x00]*$").unwrap());
+static COLOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})|(?i:(rgb|hsl)a?)\(\s*[0-9.]+(deg|%)?(\s*[, /]\s*[0-9.]+%?){2,3}\s*\))$",
+    )
+    .unwrap()
+});
+static XML: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<(\?xml|!(?i:doctype)|[A-Za-z][\w:.-]*)[\s\S]*(</[\w:.-]+\s*|/)>$").unwrap());
+
+// Indicios de cada lenguaje. Gana el que más indicios tenga, con al menos dos.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &[r"\bfn \w+", r"\blet mut\b", r"\bimpl\b", r"\bpub (fn|struct|enum|mod)\b", r"\w::\w", r"^use [\w:{}, ]+;$", r"\b(Some|None|Ok|Err)\b", r"#\[\w+"]),
+    ("python", &[r"^\s*def \w+\(.*\):$", r"^\s*(import \w+|from [\w.]+ import)", r"\bself\b", r"\belif\b", r"^\s*class \w+.*:$", r"\bNone\b", r"print\("]),
+    ("javascript", &[r"\bfunction\b", r"\b(const|let|var) \w+ =", r"=>", r"console\.\w+\(", r"===", r"\b(require|import)\b.*['\x22]", r"\bexport (default|const|function)\b"]),
+    ("typescript", &[r"\binterface \w+", r":\s*(string|number|boolean)\b", r"\b(const|let) \w+: ", r"\bexport (type|interface)\b"]),
+    ("go", &[r"^package \w+$", r"\bfunc\b", r":=", r"\bfmt\.\w+", r"\bchan\b", r"\bdefer\b"]),
+    ("c", &[r"^#include\s*[<\x22]", r"\bint main\(", r"\bprintf\(", r"\b(void|char|int|unsigned)\s+\*?\w+\s*[(=;]", r"->", r"\bNULL\b"]),
+    ("java", &[r"\bpublic (static )?(class|void)\b", r"System\.out\.", r"\bprivate \w+ \w+", r"@Override", r"\bnew \w+\("]),
+    ("sql", &[r"(?i)\bselect\b[\s\S]+\bfrom\b", r"(?i)\binsert into\b", r"(?i)\bupdate \w+ set\b", r"(?i)\bcreate (table|index)\b", r"(?i)\bwhere\b", r"(?i)\b(inner|left|right) join\b", r"(?i)\bdelete from\b"]),
+    ("shell", &[r"^#!/", r"^\$ ", r"\b(sudo|apt|dnf|pacman|cargo|git|docker|kubectl|npm|systemctl) \w", r"\|\s*(grep|awk|sed|xargs|sort|head|tail)\b", r"&&", r"\$\{?\w+\}?", r"^\s*(export|echo|cd|ls) "]),
+    ("html", &[r"(?i)<!doctype html", r"(?i)<(div|span|body|head|p|a|script|style)\b", r"(?i)</(div|span|body|html)>"]),
+    ("css", &[r"^[.#]?[\w-]+( [\w.#>:-]+)*\s*\{$", r"^\s*[\w-]+:\s*[^;]+;$", r"@media\b", r"\b\d+(px|em|rem)\b"]),
+];
+
+static LANGUAGE_PATTERNS: LazyLock<Vec<(&'static str, RegexSet)>> = LazyLock::new(|| {
+    LANGUAGES
+        .iter()
+        .map(|(language, patterns)| {
+            let patterns = patterns.iter().map(|pattern| format!("(?m){pattern}"));
+            (*language, RegexSet::new(patterns).unwrap())
+        })
+        .collect()
+});
+
+// Clasifica un texto copiado. Los formatos de un solo valor se prueban antes que
+// los de varias líneas; lo que no encaja en ninguno es prosa.
+pub fn classify(content: &str) -> Classification {
+    let text = content.trim();
+    let single_line = !text.contains('\n');
+    if single_line {
+        if URL.is_match(text) {
+            return Classification::of(Kind::Url);
+        }
+        if EMAIL.is_match(text) {
+            return Classification::of(Kind::Email);
+        }
+        if COLOR.is_match(text) {
+            return Classification::of(Kind::Color);
+        }
+        if NUMBER.is_match(text) {
+            return Classification::of(Kind::Number);
+        }
+        let digits = text.chars().filter(char::is_ascii_digit).count();
+        if PHONE.is_match(text) && (7..=15).contains(&digits) && !DATE.is_match(text) {
+            return Classification::of(Kind::Phone);
+        }
+        if PATH.is_match(text) {
+            return Classification::of(Kind::Path);
+        }
+    }
+    if text.starts_with(['{', '[']) && serde_json::from_str::<serde_json::Value>(text).is_ok() {
+        return Classification::of(Kind::Json);
+    }
+    if let Some(language) = language(text) {
+        return Classification {
+            kind: Kind::Code,
+            language: Some(language),
+        };
+    }
+    if XML.is_match(text) {
+        return Classification::of(Kind::Xml);
+    }
+    Classification::of(Kind::Prose)
+}
+
+fn language(text: &str) -> Option<&'static str> {
+    LANGUAGE_PATTERNS
+        .iter()
+        .map(|(language, patterns)| (*language, patterns.matches(text).iter().count()))
+        .filter(|(_, score)| *score >= 2)
+        .max_by_key(|(_, score)| *score)
+        .map(|(language, _)| language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(content: &str) -> Kind {
+        classify(content).kind
+    }
+
+    #[test]
+    fn single_values() {
+        assert_eq!(kind("https://example.com/a?b=c"), Kind::Url);
+        assert_eq!(kind("  www.example.org\n"), Kind::Url);
+        assert_eq!(kind("ana@example.com"), Kind::Email);
+        assert_eq!(kind("mailto:ana@example.com"), Kind::Email);
+        assert_eq!(kind("+34 612 34 56 78"), Kind::Phone);
+        assert_eq!(kind("(555) 123-4567"), Kind::Phone);
+        assert_eq!(kind("/etc/hosts"), Kind::Path);
+        assert_eq!(kind("~/Documentos/informe final.pdf"), Kind::Path);
+        assert_eq!(kind("C:\\Users\\ana"), Kind::Path);
+        assert_eq!(kind("#ff8800"), Kind::Color);
+        assert_eq!(kind("rgb(255, 136, 0)"), Kind::Color);
+        assert_eq!(kind("hsl(32deg 100% 50%)"), Kind::Color);
+        assert_eq!(kind("1.234,56"), Kind::Number);
+        assert_eq!(kind("-3.5e10"), Kind::Number);
+        assert_eq!(kind("0xFF"), Kind::Number);
+        assert_eq!(kind("2024-01-15"), Kind::Prose);
+    }
+
+    #[test]
+    fn documents() {
+        assert_eq!(kind(r#"{"name": "ropias", "tags": [1, 2]}"#), Kind::Json);
+        assert_eq!(kind("<?xml version=\"1.0\"?>\n<a><b/></a>"), Kind::Xml);
+        assert_eq!(kind("[no es json"), Kind::Prose);
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            classify("fn main() {\n    let mut x = Vec::new();\n}").language,
+            Some("rust")
+        );
+        assert_eq!(classify("def hola(self):\n    return None").language, Some("python"));
+        assert_eq!(classify("SELECT id FROM clipboard WHERE favorite = 1").language, Some("sql"));
+        assert_eq!(classify("sudo apt install libsqlite3-dev && cargo build").language, Some("shell"));
+    }
+
+    #[test]
+    fn prose() {
+        assert_eq!(kind("Nos vemos mañana a las 10, ¿vale?"), Kind::Prose);
+        assert_eq!(classify("Hola").language, None);
+    }
+}
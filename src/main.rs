@@ -1,37 +1,444 @@
 pub mod db;
+mod actions;
+mod backup;
+mod client;
+mod config;
+mod ipc;
+mod kind;
+mod migrate;
+mod preview;
+mod rules;
+mod secrets;
+mod save;
 mod server;
+mod snippets;
+mod transform;
 mod gui;
 
+use std::collections::HashMap;
 use std::error::Error;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
 
 
-use crate::db::get_clipboard_content;
+use crate::ipc::{Client, Request};
+use crate::actions::Outcome;
+use crate::backup::Format;
+use crate::config::Config;
+use crate::db::{ClearScope, PageQuery};
+use crate::kind::Kind;
+use crate::migrate::Source;
+use crate::preview::{self as text_preview, Preview};
+use crate::save::ImageFormat;
 use crate::server::server;
+use crate::snippets::Template;
+use crate::transform::Pipeline;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    // Levantar el servidor solo si se pasa el argumento "server"
     let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 && args[1] == "server" {
-        return server();
+    let config_path = config::config_path();
+    let config = match Config::load(&config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    db::set_path(config.database_path());
+
+    match args.get(1).map(String::as_str) {
+        // Levantar el servidor solo si se pasa el argumento "server"
+        Some("server") => server(config, config_path),
+        // Buscar en el historial de portapapeles si se pasa el argumento "search"
+        Some("search") | Some("list") => search(&args[2..]),
+        Some("get") => get(item_id(&args)?),
+        Some("save") => save(&args),
+        Some("export") => export(&args),
+        Some("import") => import(&args),
+        Some("copy") => copy(&args, &config),
+        Some("delete") => client::delete(item_id(&args)?),
+        Some("pin") => client::pin(item_id(&args)?, true),
+        Some("unpin") => client::pin(item_id(&args)?, false),
+        Some("tag") => client::tag(item_id(&args)?, &tag_arg(&args)?, true),
+        Some("untag") => client::tag(item_id(&args)?, &tag_arg(&args)?, false),
+        Some("tags") => tags(),
+        Some("snippet") => snippet(&args),
+        Some("trash") => trash(&args),
+        Some("clear") => clear(&args[2..]),
+        Some("watch") => watch(),
+        Some("status") => status(),
+        Some("pause") => client::pause(minutes(&args)?),
+        Some("resume") => client::resume(),
+        Some("action") => action(item_id(&args)?, args.get(3)),
+        Some("popup") => gui::popup(config),
+        Some("incognito") => client::set_incognito(args.get(2).is_none_or(|value| value != "off")),
+        // Iniciar la interfaz gráfica
+        _ => {
+            let _ = gui::show(config);
+            Ok(())
+        }
     }
+}
+
+fn item_id(args: &[String]) -> Result<i32, Box<dyn Error>> {
+    let id = args.get(2).ok_or("missing clipboard item id")?;
+    id.parse().map_err(|_| format!("invalid clipboard item id: {id}").into())
+}
 
-    // Buscar en el historial de portapapeles si se pasa el argumento "search"
-    if args.len() > 1 && args[1] == "search" {
-        return search();
+// El resto de los argumentos, para no tener que citar "customer emails".
+fn tag_arg(args: &[String]) -> Result<String, Box<dyn Error>> {
+    let tag = args.get(3..).map(|words| words.join(" ")).unwrap_or_default();
+    Ok(db::tag_name(&tag)?.to_string())
+}
+
+fn tags() -> Result<(), Box<dyn Error>> {
+    for tag in client::tags()? {
+        println!("{}\t{}", tag.name, tag.items);
     }
+    Ok(())
+}
 
-    // Iniciar la interfaz gráfica
-    let _ = gui::show();
+// `ropias clear --all | [--keep-favorites] [--type text|image|files] [--last 15m]
+// [--app firefox] [--secure]`. Sin `--secure` lo borrado queda en la papelera.
+fn clear(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut scope = ClearScope::default();
+    let mut all = false;
+    let mut secure = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value after {arg}"));
+        match arg.as_str() {
+            "--all" => all = true,
+            "--secure" => secure = true,
+            "--keep-favorites" => scope.keep_favorites = true,
+            "--type" => {
+                let content_type = value()?;
+                if !["text", "image", "files"].contains(&content_type.as_str()) {
+                    return Err(format!("unknown type {content_type:?}, expected text, image or files").into());
+                }
+                scope.content_type = Some(content_type.clone());
+            }
+            "--last" => scope.last_seconds = Some(db::parse_period(value()?)?),
+            "--app" => scope.source_app = Some(value()?.clone()),
+            _ => return Err(format!("unknown option {arg:?}").into()),
+        }
+    }
+    // Para no borrar todo el historial por olvidar una opción.
+    if !all && scope == ClearScope::default() {
+        return Err("choose what to clear: --all, --keep-favorites, --type, --last or --app".into());
+    }
+    let count = client::clear(&scope, secure)?.len();
+    let entries = if count == 1 { "entry" } else { "entries" };
+    match secure {
+        true => println!("wiped {count} {entries}"),
+        false => println!("moved {count} {entries} to the trash"),
+    }
     Ok(())
 }
 
-fn search() -> Result<(), Box<dyn Error>> {
-    get_clipboard_content()?.iter().for_each(|content| {
-        println!("{}", content.content);
+// `ropias trash [list] | restore <id>... | empty`.
+fn trash(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args.get(2).map(String::as_str).unwrap_or("list") {
+        "list" => {
+            for trashed in client::trash()? {
+                let preview = Preview::new(&trashed.item, LIST_WIDTH);
+                println!("{}\t{}\t{}", trashed.item.id, trashed.deleted_at, preview.text);
+            }
+            Ok(())
+        }
+        "restore" => {
+            let ids = args[3..]
+                .iter()
+                .map(|id| id.parse().map_err(|_| format!("invalid clipboard item id: {id}")))
+                .collect::<Result<Vec<i32>, _>>()?;
+            if ids.is_empty() {
+                return Err("missing clipboard item id".into());
+            }
+            client::restore(&ids).map(|_| ())
+        }
+        "empty" => client::empty_trash(),
+        command => Err(format!("unknown trash command {command:?}, expected list, restore or empty").into()),
+    }
+}
+
+fn minutes(args: &[String]) -> Result<Option<u64>, Box<dyn Error>> {
+    match args.get(2) {
+        Some(minutes) => Ok(Some(
+            minutes.parse().map_err(|_| format!("invalid number of minutes: {minutes}"))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+// Columnas de la vista previa en `list` y `search`; `get` muestra el contenido completo.
+const LIST_WIDTH: usize = 100;
+
+// `list` y `search` aceptan `--kind <clase>` para mostrar solo enlaces, código...
+fn search(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut kind = None;
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--kind" {
+            let name = args.next().ok_or("missing kind after --kind")?;
+            kind = Some(Kind::parse(name).ok_or_else(|| {
+                let kinds: Vec<_> = Kind::ALL.iter().map(|kind| kind.as_str()).collect();
+                format!("unknown kind {name:?}, expected one of {}", kinds.join(", "))
+            })?);
+        } else {
+            words.push(arg.as_str());
+        }
+    }
+    let query = words.join(" ");
+    let items = match kind {
+        Some(kind) => client::page(&PageQuery {
+            search: query,
+            kind: Some(kind),
+            ..PageQuery::default()
+        })?,
+        None if query.is_empty() => client::list(None)?,
+        None => client::search(&query, None)?,
+    };
+    items.iter().for_each(|item| match item.content_type.as_str() {
+        "image" => println!("{}\timage\t[{}]", item.id, item.mime),
+        _ if item.secret => println!("{}\tsecret\t[secret]", item.id),
+        content_type => {
+            let kind = item.kind.map(Kind::as_str).unwrap_or(content_type);
+            let preview = Preview::new(item, LIST_WIDTH);
+            match preview.lines {
+                1 => println!("{}\t{kind}\t{}", item.id, preview.text),
+                lines => {
+                    let unit = if item.content_type == "files" { "files" } else { "lines" };
+                    println!("{}\t{kind}\t{} ({lines} {unit})", item.id, preview.text)
+                }
+            }
+        }
     });
     Ok(())
 }
 
+// Sin nombre muestra las acciones disponibles para el elemento.
+fn action(id: i32, name: Option<&String>) -> Result<(), Box<dyn Error>> {
+    let Some(name) = name else {
+        let item = client::get(id)?.ok_or_else(|| format!("clipboard item {id} not found"))?;
+        for action in actions::available(&item) {
+            println!("{}\t{}", action.name, action.label);
+        }
+        return Ok(());
+    };
+    if let Outcome::Text(content) = client::action(id, name)? {
+        println!("{content}");
+    }
+    Ok(())
+}
+
+// `--transform trim,lower` copia el resultado de las transformaciones como elemento nuevo.
+fn copy(args: &[String], config: &Config) -> Result<(), Box<dyn Error>> {
+    let id = item_id(args)?;
+    match args.iter().position(|arg| arg == "--transform") {
+        Some(i) => {
+            let spec = args.get(i + 1).ok_or("missing transforms after --transform")?;
+            let pipeline = Pipeline::parse(spec, &config.transforms)?;
+            client::copy_transformed(id, &pipeline).map(|_| ())
+        }
+        None => client::set_clipboard(id, args.iter().any(|arg| arg == "--plain")),
+    }
+}
+
+// `ropias save <id> [ruta] [--format png|jpeg|webp]`
+fn save(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let id = item_id(args)?;
+    let mut target = None;
+    let mut format = None;
+    let mut rest = args[3..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--format" {
+            let name = rest.next().ok_or("missing format after --format")?;
+            format = Some(ImageFormat::parse(name).ok_or_else(|| format!("unknown image format {name:?}, expected png, jpeg or webp"))?);
+        } else {
+            target = Some(PathBuf::from(arg));
+        }
+    }
+    let item = client::get(id)?.ok_or_else(|| format!("clipboard item {id} not found"))?;
+    for path in save::save(&item, target.as_deref(), format)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+// El formato sale de `--format` o de la extensión del archivo.
+fn backup_args(args: &[String]) -> Result<(PathBuf, Format), Box<dyn Error>> {
+    let mut path = None;
+    let mut format = None;
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = rest.next().ok_or("missing format after --format")?;
+                format = Some(Format::parse(name).ok_or_else(|| format!("unknown format {name:?}, expected jsonl, csv or tar"))?);
+            }
+            "--secrets" => {}
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+    let path = path.ok_or("missing file path")?;
+    let format = format
+        .or_else(|| Format::from_path(&path))
+        .ok_or("cannot tell the format from the file name, use --format jsonl, csv or tar")?;
+    Ok((path, format))
+}
+
+// `ropias export <archivo> [--format jsonl|csv|tar] [--secrets]`
+fn export(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (path, format) = backup_args(args)?;
+    let report = backup::export(&path, format, args.iter().any(|arg| arg == "--secrets"))?;
+    println!("{report}");
+    Ok(())
+}
+
+// `ropias import <archivo>` o `ropias import --from cliphist|copyq|gpaste|clipcat [ruta]`
+fn import(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.get(2).is_some_and(|arg| arg == "--from") {
+        let name = args.get(3).ok_or("missing clipboard manager after --from")?;
+        let source = Source::parse(name)
+            .ok_or_else(|| format!("unknown clipboard manager {name:?}, expected cliphist, copyq, gpaste or clipcat"))?;
+        println!("{}", migrate::import(source, args.get(4).map(Path::new))?);
+        return Ok(());
+    }
+    let (path, format) = backup_args(args)?;
+    println!("{}", backup::import(&path, format)?);
+    Ok(())
+}
+
+// `ropias snippet list [búsqueda] | show <snippet> | add <nombre> [cuerpo] |
+// edit <snippet> [cuerpo] | rename <snippet> <nombre> | delete <snippet> |
+// copy <snippet> [Etiqueta=valor...] | expand <snippet> [Etiqueta=valor...]`.
+// Un snippet se indica por id o por nombre; sin cuerpo, se lee de la entrada estándar.
+fn snippet(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let command = args.get(2).map(String::as_str).unwrap_or("list");
+    let rest = args.get(3..).unwrap_or_default();
+    match command {
+        "list" | "search" => {
+            for snippet in db::search_snippets(&rest.join(" "))? {
+                let body = text_preview::truncate(&text_preview::normalize(&snippet.body), LIST_WIDTH);
+                println!("{}\t{}\t{body}", snippet.id, snippet.name);
+            }
+            Ok(())
+        }
+        "add" => {
+            let name = rest.first().ok_or("missing snippet name")?.trim();
+            if name.is_empty() {
+                return Err("empty snippet name".into());
+            }
+            let body = snippet_body(rest.get(1))?;
+            println!("{}", db::add_snippet(name, &body)?);
+            Ok(())
+        }
+        "edit" => {
+            let snippet = find_snippet(rest.first())?;
+            db::update_snippet(snippet.id, None, Some(&snippet_body(rest.get(1))?))?;
+            Ok(())
+        }
+        "rename" => {
+            let snippet = find_snippet(rest.first())?;
+            let name = rest.get(1).map(|name| name.trim()).filter(|name| !name.is_empty());
+            db::update_snippet(snippet.id, Some(name.ok_or("missing new snippet name")?), None)?;
+            Ok(())
+        }
+        "delete" => {
+            db::delete_snippet(find_snippet(rest.first())?.id)?;
+            Ok(())
+        }
+        "show" => {
+            println!("{}", find_snippet(rest.first())?.body);
+            Ok(())
+        }
+        "copy" | "expand" => {
+            let snippet = find_snippet(rest.first())?;
+            let inputs = snippet_inputs(&snippet.body, rest.get(1..).unwrap_or_default())?;
+            let expanded = client::expand_snippet(&snippet, inputs)?;
+            match command {
+                "copy" => client::copy_text(&expanded).map(|_| ()),
+                _ => {
+                    println!("{expanded}");
+                    Ok(())
+                }
+            }
+        }
+        _ => Err(format!("unknown snippet command {command:?}, expected list, show, add, edit, rename, delete, copy or expand").into()),
+    }
+}
+
+fn find_snippet(id_or_name: Option<&String>) -> Result<db::Snippet, Box<dyn Error>> {
+    let id_or_name = id_or_name.ok_or("missing snippet id or name")?;
+    db::find_snippet(id_or_name)?.ok_or_else(|| format!("snippet {id_or_name:?} not found").into())
+}
+
+// Se valida al guardar, para no encontrar el error al copiarlo.
+fn snippet_body(body: Option<&String>) -> Result<String, Box<dyn Error>> {
+    let body = match body {
+        Some(body) => body.clone(),
+        None => {
+            let mut body = String::new();
+            io::stdin().read_to_string(&mut body)?;
+            body.strip_suffix('\n').map(String::from).unwrap_or(body)
+        }
+    };
+    Template::parse(&body)?;
+    Ok(body)
+}
+
+// Las respuestas a `{{input:...}}` vienen como `Etiqueta=valor`; las que faltan se preguntan.
+fn snippet_inputs(body: &str, args: &[String]) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut inputs: HashMap<String, String> = args
+        .iter()
+        .filter_map(|arg| arg.split_once('='))
+        .map(|(label, value)| (label.to_string(), value.to_string()))
+        .collect();
+    for label in Template::parse(body)?.inputs() {
+        if inputs.contains_key(label) {
+            continue;
+        }
+        eprint!("{label}: ");
+        io::stderr().flush()?;
+        let mut value = String::new();
+        io::stdin().lock().read_line(&mut value)?;
+        inputs.insert(label.to_string(), value.trim_end_matches(['\r', '\n']).to_string());
+    }
+    Ok(inputs)
+}
+
+fn get(id: i32) -> Result<(), Box<dyn Error>> {
+    let item = client::get(id)?.ok_or_else(|| format!("clipboard item {id} not found"))?;
+    println!("{}", item.content);
+    Ok(())
+}
+
+fn status() -> Result<(), Box<dyn Error>> {
+    match client::status() {
+        Some(status) => {
+            println!("daemon: running (version {}, protocol {})", status.version, status.protocol);
+            println!("capture: {}", status.capture_state());
+            println!("items: {}", status.items);
+        }
+        None => {
+            println!("daemon: not running");
+            println!("items: {}", db::count_clipboard_items()?);
+        }
+    }
+    Ok(())
+}
+
+// Muestra los eventos del daemon según ocurren, una línea JSON por evento.
+fn watch() -> Result<(), Box<dyn Error>> {
+    let mut client = Client::connect().ok_or("the daemon is not running")?;
+    client.request(Request::Subscribe)?;
+    while let Some(event) = client.next_event()? {
+        println!("{}", serde_json::to_string(&event)?);
+    }
+    Ok(())
+}
+
 
 // Copiar imagen
 // let mut ctx = Clipboard::new().unwrap();
@@ -0,0 +1,132 @@
+use std::error::Error;
+
+use super::Entry;
+use crate::backup::ImportReport;
+
+// clipcatd guarda su historial (`~/.cache/clipcat/clipcatd-history`) con bincode:
+// una cabecera con el esquema y la fecha de la última escritura, y la lista de
+// elementos, cada uno con su contenido, su tipo MIME y la fecha en que se copió.
+// Los enteros van en little-endian y las listas y textos llevan delante su
+// largo en 8 bytes. Si el archivo no tiene exactamente esa forma se rechaza,
+// porque es un formato interno que puede cambiar entre versiones.
+pub(super) fn read(file: &[u8], report: &mut ImportReport) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut reader = Reader { file, position: 0 };
+    let schema = reader.u64().ok_or("the clipcat history is empty")?;
+    let unsupported = || format!("unsupported clipcat history file (schema {schema})");
+    reader.timestamp().ok_or_else(unsupported)?;
+    let count = reader.u64().ok_or_else(unsupported)?;
+    let mut clips = Vec::new();
+    for _ in 0..count {
+        let data = reader.bytes().ok_or_else(unsupported)?;
+        let mime = reader.bytes().and_then(|mime| std::str::from_utf8(mime).ok()).ok_or_else(unsupported)?;
+        let copied_at = reader.timestamp().ok_or_else(unsupported)?;
+        clips.push((copied_at, mime, data));
+    }
+    if reader.position != file.len() {
+        return Err(unsupported().into());
+    }
+    clips.sort_by_key(|(copied_at, _, _)| *copied_at);
+    let mut entries = Vec::new();
+    for (copied_at, mime, data) in clips {
+        let entry = match mime.starts_with("image/") {
+            true => Entry::image(data),
+            false => String::from_utf8(data.to_vec()).ok().filter(|text| !text.is_empty()).map(Entry::text),
+        };
+        match entry {
+            Some(entry) => entries.push(Entry {
+                copied_at: Some(copied_at),
+                ..entry
+            }),
+            None => report.invalid.push(format!("clipcat item copied at {copied_at}: unsupported {mime} content")),
+        }
+    }
+    Ok(entries)
+}
+
+struct Reader<'a> {
+    file: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.file.get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+        Some(bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u64()?;
+        self.take(usize::try_from(len).ok()?)
+    }
+
+    // Una fecha de `time` en serde compacto: año (4 bytes), día del año (2), hora,
+    // minuto, segundo, nanosegundos (4) y el desfase en horas, minutos y segundos.
+    // Devuelve los segundos desde 1970.
+    fn timestamp(&mut self) -> Option<i64> {
+        let bytes = self.take(16)?;
+        let year = i64::from(i32::from_le_bytes(bytes[0..4].try_into().ok()?));
+        let ordinal = i64::from(u16::from_le_bytes(bytes[4..6].try_into().ok()?));
+        let (hour, minute, second) = (i64::from(bytes[6]), i64::from(bytes[7]), i64::from(bytes[8]));
+        let offset = i64::from(bytes[13] as i8) * 3600 + i64::from(bytes[14] as i8) * 60 + i64::from(bytes[15] as i8);
+        if !(1..=366).contains(&ordinal) || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let days = days_before(year) - days_before(1970) + ordinal - 1;
+        Some(days * 86_400 + hour * 3600 + minute * 60 + second - offset)
+    }
+}
+
+// Días desde el comienzo del calendario hasta el 1 de enero de `year`.
+fn days_before(year: i64) -> i64 {
+    let year = year - 1;
+    year * 365 + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-31 (día 31) a las 23:59:59 UTC, o una hora más tarde en UTC+1.
+    fn timestamp(hour: u8, offset: i8) -> Vec<u8> {
+        let mut bytes = 2024i32.to_le_bytes().to_vec();
+        bytes.extend(31u16.to_le_bytes());
+        bytes.extend([hour, 59, 59]);
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend([offset as u8, 0, 0]);
+        bytes
+    }
+
+    fn bytes(value: &[u8]) -> Vec<u8> {
+        [&(value.len() as u64).to_le_bytes(), value].concat()
+    }
+
+    #[test]
+    fn reads_history_file() {
+        let mut file = 2u64.to_le_bytes().to_vec();
+        file.extend(timestamp(23, 0));
+        file.extend(3u64.to_le_bytes());
+        for (data, mime, copied_at) in [
+            (&b"segundo"[..], "text/plain; charset=utf-8", timestamp(23, 0)),
+            (b"primero", "text/plain; charset=utf-8", timestamp(23, 1)),
+            (b"not a png", "image/png", timestamp(23, 0)),
+        ] {
+            file.extend(bytes(data));
+            file.extend(bytes(mime.as_bytes()));
+            file.extend(copied_at);
+        }
+        let mut report = ImportReport::default();
+        let entries = read(&file, &mut report).unwrap();
+        let contents: Vec<_> = entries.iter().map(|entry| (entry.content.as_str(), entry.copied_at)).collect();
+        assert_eq!(contents, [("primero", Some(1_706_741_999)), ("segundo", Some(1_706_745_599))]);
+        assert_eq!(report.invalid.len(), 1);
+
+        file.push(0);
+        assert!(read(&file, &mut report).is_err());
+        assert!(read(&file[..40], &mut report).is_err());
+    }
+}
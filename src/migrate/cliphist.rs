@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+use super::Entry;
+use crate::backup::ImportReport;
+
+// cliphist guarda cada elemento en la cubeta "b" de una base bbolt: la clave es un
+// número creciente de 8 bytes y el valor, lo copiado tal cual. No guarda fechas.
+const BUCKET: &[u8] = b"b";
+
+const MAGIC: u32 = 0xED0C_DAED;
+const PAGE_HEADER: usize = 16;
+const ELEMENT: usize = 16;
+const BRANCH_PAGE: u16 = 0x01;
+const LEAF_PAGE: u16 = 0x02;
+const BUCKET_LEAF: u32 = 0x01;
+// Un árbol de bbolt con millones de elementos tiene pocos niveles; más que esto
+// es un archivo dañado.
+const MAX_DEPTH: usize = 32;
+
+pub(super) fn read(file: &[u8], report: &mut ImportReport) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let db = Bolt::open(file)?;
+    let mut buckets = Vec::new();
+    db.leaves(db.page(db.root)?, &mut buckets)?;
+    let (_, bucket) = buckets
+        .into_iter()
+        .find(|(key, (flags, _))| *key == BUCKET && flags & BUCKET_LEAF != 0)
+        .ok_or("this is not a cliphist database: it has no \"b\" bucket")?;
+    let mut items = Vec::new();
+    db.leaves(db.bucket_root(bucket.1)?, &mut items)?;
+    // Las claves van en orden, de la más vieja a la más nueva.
+    let mut entries = Vec::new();
+    for (key, (_, value)) in items {
+        match Entry::guess(value.to_vec()) {
+            Some(entry) => entries.push(entry),
+            None => report.invalid.push(format!("cliphist item {}: unsupported content", id(key))),
+        }
+    }
+    Ok(entries)
+}
+
+fn id(key: &[u8]) -> u64 {
+    key.try_into().map_or(0, u64::from_be_bytes)
+}
+
+// Lo justo de bbolt para leer una base sin escribir en ella.
+struct Bolt<'a> {
+    file: &'a [u8],
+    page_size: usize,
+    root: u64,
+}
+
+type Leaf<'a> = (&'a [u8], (u32, &'a [u8]));
+
+impl<'a> Bolt<'a> {
+    // Hay dos páginas meta; vale la de la última transacción.
+    fn open(file: &'a [u8]) -> Result<Bolt<'a>, Box<dyn Error>> {
+        let page_size = u32_at(file, PAGE_HEADER + 8).ok_or("the cliphist database is empty")? as usize;
+        let meta = |page: usize| {
+            let meta = page * page_size + PAGE_HEADER;
+            (u32_at(file, meta)? == MAGIC).then_some((u64_at(file, meta + 48)?, u64_at(file, meta + 16)?))
+        };
+        let (_, root) = [meta(0), meta(1)]
+            .into_iter()
+            .flatten()
+            .max()
+            .ok_or("this is not a bbolt database")?;
+        Ok(Bolt { file, page_size, root })
+    }
+
+    fn page(&self, id: u64) -> Result<&'a [u8], Box<dyn Error>> {
+        let start = (id as usize).checked_mul(self.page_size).filter(|start| *start < self.file.len());
+        Ok(&self.file[start.ok_or_else(|| format!("page {id} is outside the cliphist database"))?..])
+    }
+
+    // Una cubeta pequeña guarda su página dentro del propio valor.
+    fn bucket_root(&self, value: &'a [u8]) -> Result<&'a [u8], Box<dyn Error>> {
+        match u64_at(value, 0) {
+            Some(0) => Ok(value.get(16..).ok_or("invalid bucket in the cliphist database")?),
+            Some(root) => self.page(root),
+            None => Err("invalid bucket in the cliphist database".into()),
+        }
+    }
+
+    fn leaves(&self, page: &'a [u8], out: &mut Vec<Leaf<'a>>) -> Result<(), Box<dyn Error>> {
+        self.walk(page, 0, &mut HashSet::new(), out)
+    }
+
+    // En un archivo dañado una rama podría apuntar a sí misma o a una página ya leída.
+    fn walk(&self, page: &'a [u8], depth: usize, visited: &mut HashSet<u64>, out: &mut Vec<Leaf<'a>>) -> Result<(), Box<dyn Error>> {
+        let invalid = || "corrupt page in the cliphist database";
+        let flags = u16_at(page, 8).ok_or_else(invalid)?;
+        let count = u16_at(page, 10).ok_or_else(invalid)? as usize;
+        for i in 0..count {
+            let element = PAGE_HEADER + i * ELEMENT;
+            if flags & BRANCH_PAGE != 0 {
+                let child = u64_at(page, element + 8).ok_or_else(invalid)?;
+                if depth >= MAX_DEPTH || !visited.insert(child) {
+                    return Err(invalid().into());
+                }
+                self.walk(self.page(child)?, depth + 1, visited, out)?;
+            } else if flags & LEAF_PAGE != 0 {
+                let field = |offset| u32_at(page, element + offset).map(|value| value as usize).ok_or_else(invalid);
+                let (start, key_size, value_size) = (element + field(4)?, field(8)?, field(12)?);
+                let key = page.get(start..start + key_size).ok_or_else(invalid)?;
+                let value = page.get(start + key_size..start + key_size + value_size).ok_or_else(invalid)?;
+                out.push((key, (field(0)? as u32, value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: usize = 4096;
+
+    // Una página hoja con pares clave/valor.
+    fn leaf(items: &[(&[u8], &[u8], u32)]) -> Vec<u8> {
+        let mut page = vec![0; PAGE_HEADER];
+        page[8..10].copy_from_slice(&LEAF_PAGE.to_le_bytes());
+        page[10..12].copy_from_slice(&(items.len() as u16).to_le_bytes());
+        let mut data = Vec::new();
+        for (i, (key, value, flags)) in items.iter().enumerate() {
+            let position = (items.len() - i) * ELEMENT + data.len();
+            for field in [*flags, position as u32, key.len() as u32, value.len() as u32] {
+                page.extend_from_slice(&field.to_le_bytes());
+            }
+            data.extend_from_slice(key);
+            data.extend_from_slice(value);
+        }
+        page.extend(data);
+        page
+    }
+
+    fn meta(txid: u64, root: u64) -> Vec<u8> {
+        let mut page = vec![0; PAGE];
+        page[8..10].copy_from_slice(&0x04u16.to_le_bytes());
+        page[16..20].copy_from_slice(&MAGIC.to_le_bytes());
+        page[24..28].copy_from_slice(&(PAGE as u32).to_le_bytes());
+        page[32..40].copy_from_slice(&root.to_le_bytes());
+        page[64..72].copy_from_slice(&txid.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn reads_inline_bucket() {
+        let mut bucket = vec![0; 16];
+        bucket.extend(leaf(&[(&1u64.to_be_bytes(), b"primero", 0), (&2u64.to_be_bytes(), b"segundo", 0)]));
+        let mut root = leaf(&[(b"b", &bucket, BUCKET_LEAF)]);
+        root.resize(PAGE, 0);
+        // La meta más nueva apunta a la página 2; la vieja, a una que no existe.
+        let file = [meta(1, 9), meta(2, 2), root].concat();
+        let mut report = ImportReport::default();
+        let entries = read(&file, &mut report).unwrap();
+        let contents: Vec<_> = entries.iter().map(|entry| entry.content.as_str()).collect();
+        assert_eq!(contents, ["primero", "segundo"]);
+    }
+
+    #[test]
+    fn rejects_branch_loops() {
+        // La raíz es una rama cuyo único hijo es ella misma.
+        let mut root = vec![0; PAGE_HEADER + ELEMENT];
+        root[8..10].copy_from_slice(&BRANCH_PAGE.to_le_bytes());
+        root[10..12].copy_from_slice(&1u16.to_le_bytes());
+        root[PAGE_HEADER + 8..PAGE_HEADER + 16].copy_from_slice(&2u64.to_le_bytes());
+        root.resize(PAGE, 0);
+        let file = [meta(1, 2), meta(0, 2), root].concat();
+        let mut report = ImportReport::default();
+        assert!(read(&file, &mut report).is_err());
+    }
+
+    #[test]
+    fn rejects_short_buckets() {
+        let mut root = leaf(&[(b"b", &[0; 12], BUCKET_LEAF)]);
+        root.resize(PAGE, 0);
+        let file = [meta(1, 2), meta(0, 2), root].concat();
+        let mut report = ImportReport::default();
+        assert!(read(&file, &mut report).is_err());
+    }
+}
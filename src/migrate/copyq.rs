@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+
+use super::Entry;
+use crate::backup::ImportReport;
+
+// Marca que agrega el plugin de elementos fijados de CopyQ.
+const PINNED: &str = "application/x-copyq-item-pinned";
+
+// Lee un archivo de pestaña (copyq_tab_*.dat) o todos los de una carpeta. CopyQ no
+// guarda fechas; cada pestaña lista primero lo más nuevo.
+pub(super) fn read(path: &Path, report: &mut ImportReport) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let files = if path.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                name.starts_with("copyq_tab_") && name.ends_with(".dat")
+            })
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+    if files.is_empty() {
+        return Err(format!("no CopyQ tabs (copyq_tab_*.dat) in {}", path.display()).into());
+    }
+    let mut entries = Vec::new();
+    for file in files {
+        let name = file.display().to_string();
+        match parse(&fs::read(&file)?) {
+            Ok(items) => {
+                for (i, item) in items.into_iter().enumerate().rev() {
+                    match entry(item) {
+                        Some(entry) => entries.push(entry),
+                        None => report.invalid.push(format!("{name}, row {}: no text, image or files", i + 1)),
+                    }
+                }
+            }
+            Err(err) => report.invalid.push(format!("{name}: {err}")),
+        }
+    }
+    Ok(entries)
+}
+
+// Cada elemento es un mapa de tipo MIME a datos, como en el portapapeles.
+type Item = BTreeMap<String, Vec<u8>>;
+
+fn entry(mut item: Item) -> Option<Entry> {
+    let pinned = item.contains_key(PINNED);
+    let uris = item.remove("text/uri-list").and_then(|data| String::from_utf8(data).ok());
+    let image = item.iter().find(|(mime, _)| mime.starts_with("image/")).map(|(_, data)| data);
+    let mut entry = match (uris.and_then(|uris| super::capture::file_list(&uris)), image) {
+        (Some(paths), _) => Entry::files(paths),
+        (None, Some(data)) => Entry::image(data)?,
+        (None, None) => Entry::text(String::from_utf8(item.remove("text/plain")?).ok()?),
+    };
+    entry.pinned = pinned;
+    Some(entry)
+}
+
+// Formato de QDataStream: una cabecera "CopyQ v…", la cantidad de elementos y, por
+// cada uno, la marca -2, la cantidad de tipos y tripletas (tipo abreviado, si va
+// comprimido, datos). Todo en big endian.
+fn parse(file: &[u8]) -> Result<Vec<Item>, Box<dyn Error>> {
+    let mut stream = Stream(file);
+    let header = stream.string()?;
+    if !header.starts_with("CopyQ") {
+        return Err("not a CopyQ tab file".into());
+    }
+    let count = stream.i32()?;
+    let mut items = Vec::new();
+    for _ in 0..count {
+        if stream.i32()? != -2 {
+            return Err("unsupported CopyQ item format, save the tab with CopyQ 3 or newer".into());
+        }
+        let mut item = BTreeMap::new();
+        for _ in 0..stream.i32()? {
+            let mime = expand_mime(&stream.string()?);
+            let compressed = stream.bool()?;
+            let mut data = stream.bytes()?;
+            if compressed {
+                data = uncompress(&data)?;
+            }
+            item.insert(mime, data);
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// CopyQ acorta los tipos más comunes con un prefijo numérico.
+fn expand_mime(mime: &str) -> String {
+    match mime.split_at_checked(1) {
+        Some(("0", rest)) => format!("application/x-copyq-{rest}"),
+        Some(("1", rest)) => format!("text/{rest}"),
+        Some(("2", rest)) => rest.to_string(),
+        _ => mime.to_string(),
+    }
+}
+
+// qCompress: el tamaño original en 4 bytes y después zlib.
+fn uncompress(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut uncompressed = Vec::new();
+    ZlibDecoder::new(data.get(4..).unwrap_or_default()).read_to_end(&mut uncompressed)?;
+    Ok(uncompressed)
+}
+
+struct Stream<'a>(&'a [u8]);
+
+impl Stream<'_> {
+    fn take(&mut self, size: usize) -> Result<&[u8], Box<dyn Error>> {
+        if self.0.len() < size {
+            return Err("truncated CopyQ tab file".into());
+        }
+        let (taken, rest) = self.0.split_at(size);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, Box<dyn Error>> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn bool(&mut self) -> Result<bool, Box<dyn Error>> {
+        Ok(self.take(1)?[0] != 0)
+    }
+
+    // 0xFFFFFFFF es un QByteArray nulo.
+    fn bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self.u32()? {
+            u32::MAX => Ok(Vec::new()),
+            size => Ok(self.take(size as usize)?.to_vec()),
+        }
+    }
+
+    // QString: tamaño en bytes y UTF-16.
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let bytes = self.bytes()?;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+        Ok(String::from_utf16(&units)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn string(out: &mut Vec<u8>, text: &str) {
+        let units: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        bytes(out, &units);
+    }
+
+    fn bytes(out: &mut Vec<u8>, data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(data);
+    }
+
+    #[test]
+    fn reads_tab() {
+        let mut file = Vec::new();
+        string(&mut file, "CopyQ v3");
+        file.extend_from_slice(&2i32.to_be_bytes());
+        // Lo más nuevo, fijado y comprimido.
+        file.extend_from_slice(&(-2i32).to_be_bytes());
+        file.extend_from_slice(&2i32.to_be_bytes());
+        string(&mut file, "1plain");
+        file.push(1);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all("segundo".as_bytes()).unwrap();
+        bytes(&mut file, &[&7u32.to_be_bytes()[..], &encoder.finish().unwrap()].concat());
+        string(&mut file, "0item-pinned");
+        file.push(0);
+        bytes(&mut file, b"");
+        // Lo más viejo.
+        file.extend_from_slice(&(-2i32).to_be_bytes());
+        file.extend_from_slice(&1i32.to_be_bytes());
+        string(&mut file, "1plain");
+        file.push(0);
+        bytes(&mut file, b"primero");
+
+        let items = parse(&file).unwrap();
+        assert_eq!(items[0]["text/plain"], b"segundo");
+        assert!(items[0].contains_key(PINNED));
+        let entries: Vec<_> = items.into_iter().rev().filter_map(entry).collect();
+        assert_eq!(entries[0].content, "primero");
+        assert!(!entries[0].pinned);
+        assert_eq!(entries[1].content, "segundo");
+        assert!(entries[1].pinned);
+    }
+}
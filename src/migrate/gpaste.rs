@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::fs;
+
+use super::Entry;
+use crate::backup::ImportReport;
+
+// history.xml de GPaste: `<item kind="Text|Uris|Image|Password">` con el contenido
+// en `<value>`, lo más nuevo primero. Las imágenes guardan la ruta del PNG y la
+// fecha en que se copiaron.
+pub(super) fn read(xml: &str, report: &mut ImportReport) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let document = roxmltree::Document::parse(xml)?;
+    let history = document.root_element();
+    if !history.has_tag_name("history") {
+        return Err("not a GPaste history: the root element is not <history>".into());
+    }
+    let mut entries = Vec::new();
+    for (i, item) in history.children().filter(|node| node.has_tag_name("item")).enumerate() {
+        let kind = item.attribute("kind").unwrap_or("Text");
+        // La versión 1.0 guardaba el contenido directamente en <item>.
+        let value: String = item
+            .children()
+            .find(|node| node.has_tag_name("value"))
+            .unwrap_or(item)
+            .descendants()
+            .filter(|node| node.is_text())
+            .filter_map(|node| node.text())
+            .collect();
+        let entry = match kind {
+            // Entry::text reconoce las listas de URIs file://.
+            "Text" | "Uris" => Some(Entry::text(value)),
+            "Password" => Some(Entry { secret: true, ..Entry::text(value) }),
+            "Image" => fs::read(value.trim()).ok().and_then(|data| Entry::image(&data)),
+            _ => None,
+        };
+        match entry {
+            Some(mut entry) => {
+                entry.copied_at = item.attribute("date").and_then(|date| date.parse().ok());
+                entries.push(entry);
+            }
+            None => report.invalid.push(format!("GPaste item {}: cannot read this {kind} item", i + 1)),
+        }
+    }
+    entries.reverse();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_history() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<history version="2.0">
+  <item kind="Password" uuid="3" name="banco"><value><![CDATA[hunter2]]></value></item>
+  <item kind="Image" uuid="2" date="1706745599"><value><![CDATA[/no/existe.png]]></value></item>
+  <item kind="Text" uuid="1"><value><![CDATA[a < b]]></value></item>
+</history>"#;
+        let mut report = ImportReport::default();
+        let entries = read(xml, &mut report).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content, "a < b");
+        assert!(entries[1].secret);
+        assert_eq!(report.invalid.len(), 1);
+    }
+}
@@ -0,0 +1,204 @@
+mod cliphist;
+mod clipcat;
+mod copyq;
+mod gpaste;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use directories_next::BaseDirs;
+
+use crate::backup::{self, ImportReport, Record};
+use crate::server::capture;
+
+// Otros gestores de portapapeles desde los que se puede traer el historial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Cliphist,
+    Copyq,
+    Gpaste,
+    Clipcat,
+}
+
+impl Source {
+    pub const ALL: [Source; 4] = [Source::Cliphist, Source::Copyq, Source::Gpaste, Source::Clipcat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Cliphist => "cliphist",
+            Source::Copyq => "copyq",
+            Source::Gpaste => "gpaste",
+            Source::Clipcat => "clipcat",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Source> {
+        Source::ALL.into_iter().find(|source| source.name() == name.to_lowercase())
+    }
+
+    // Donde cada uno guarda su historial por omisión.
+    fn default_path(self) -> Option<PathBuf> {
+        let dirs = BaseDirs::new()?;
+        Some(match self {
+            Source::Cliphist => dirs.cache_dir().join("cliphist/db"),
+            Source::Copyq => dirs.config_dir().join("copyq"),
+            Source::Gpaste => dirs.data_dir().join("gpaste/history.xml"),
+            Source::Clipcat => dirs.cache_dir().join("clipcat/clipcatd-history"),
+        })
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Un elemento leído de otro gestor, antes de pasarlo a un `Record`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    content_type: &'static str,
+    mime: String,
+    content: String,
+    data: Option<Vec<u8>>,
+    // Segundos desde 1970, si el otro gestor los guarda.
+    copied_at: Option<i64>,
+    pinned: bool,
+    secret: bool,
+}
+
+impl Entry {
+    fn text(content: String) -> Entry {
+        match capture::file_list(&content) {
+            Some(paths) => Entry::files(paths),
+            None => Entry {
+                content_type: "text",
+                mime: "text/plain".to_string(),
+                content,
+                data: None,
+                copied_at: None,
+                pinned: false,
+                secret: false,
+            },
+        }
+    }
+
+    fn files(paths: String) -> Entry {
+        Entry {
+            content_type: "files",
+            mime: "text/uri-list".to_string(),
+            content: paths,
+            data: None,
+            copied_at: None,
+            pinned: false,
+            secret: false,
+        }
+    }
+
+    // Las imágenes se guardan como PNG, igual que las que copia el daemon.
+    fn image(data: &[u8]) -> Option<Entry> {
+        let image = image::load_from_memory(data).ok()?;
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+        Some(Entry {
+            content_type: "image",
+            mime: "image/png".to_string(),
+            content: String::new(),
+            data: Some(png),
+            copied_at: None,
+            pinned: false,
+            secret: false,
+        })
+    }
+
+    // Contenido sin tipo: una imagen si lo parece, si no texto.
+    fn guess(data: Vec<u8>) -> Option<Entry> {
+        if image::guess_format(&data).is_ok() {
+            return Entry::image(&data);
+        }
+        String::from_utf8(data).ok().filter(|text| !text.is_empty()).map(Entry::text)
+    }
+}
+
+// Trae el historial de otro gestor. `path` reemplaza la ubicación por omisión.
+pub fn import(source: Source, path: Option<&Path>) -> Result<ImportReport, Box<dyn Error>> {
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => source.default_path(),
+    };
+    let mut report = ImportReport::default();
+    let entries = match (source, &path) {
+        (_, None) => return Err(format!("cannot find the {source} history, pass its path").into()),
+        (Source::Cliphist, Some(path)) => cliphist::read(&fs::read(path)?, &mut report)?,
+        (Source::Clipcat, Some(path)) => clipcat::read(&fs::read(path)?, &mut report)?,
+        (Source::Copyq, Some(path)) => copyq::read(path, &mut report)?,
+        (Source::Gpaste, Some(path)) => gpaste::read(&fs::read_to_string(path)?, &mut report)?,
+    };
+    // Sin fecha, los elementos quedan un segundo antes que el siguiente, terminando
+    // en la última modificación del historial; así se conserva el orden.
+    let newest = path
+        .and_then(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .unwrap_or_else(SystemTime::now)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let count = entries.len() as i64;
+    for (i, entry) in entries.into_iter().enumerate() {
+        let copied_at = entry.copied_at.unwrap_or(newest - (count - 1 - i as i64));
+        let record = Record {
+            content_type: entry.content_type.to_string(),
+            mime: entry.mime,
+            created_at: timestamp(copied_at),
+            favorite: entry.pinned,
+            source_app: None,
+            secret: entry.secret,
+            tags: Vec::new(),
+            blob: None,
+            content: entry.content,
+        };
+        backup::merge(&record, entry.data.as_deref(), &mut report)?;
+    }
+    Ok(report)
+}
+
+// "2024-01-31 23:59:59" en UTC, como `datetime('now')` de SQLite.
+fn timestamp(seconds: i64) -> String {
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    // Fecha civil a partir de los días desde 1970 (algoritmo de Howard Hinnant).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(timestamp(1_706_745_599), "2024-01-31 23:59:59");
+    }
+
+    #[test]
+    fn sources() {
+        assert_eq!(Source::parse("CopyQ"), Some(Source::Copyq));
+        assert_eq!(Source::parse("klipper"), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::db::ClipboardItem;
+
+// Marcas visibles de los saltos de línea y los tabuladores en una vista previa.
+pub const NEWLINE_MARKER: &str = " ⏎ ";
+pub const TAB_MARKER: &str = " ⇥ ";
+const ELLIPSIS: char = '…';
+// Lo que se muestra en lugar del contenido de un secreto.
+pub const SECRET_MASK: &str = "••••••••";
+
+// Resumen de una línea de un elemento del historial, para la interfaz y la CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub text: String,
+    // Líneas del contenido original, sin contar las vacías del principio y el final.
+    pub lines: usize,
+}
+
+impl Preview {
+    pub fn new(item: &ClipboardItem, width: usize) -> Self {
+        if item.secret {
+            return Preview {
+                text: SECRET_MASK.to_string(),
+                lines: 1,
+            };
+        }
+        match item.content_type.as_str() {
+            "image" => Preview {
+                text: item.mime.clone(),
+                lines: 1,
+            },
+            "files" => {
+                let names: Vec<_> = item
+                    .content
+                    .lines()
+                    .map(|path| {
+                        Path::new(path)
+                            .file_name()
+                            .map_or(path.into(), |name| name.to_string_lossy())
+                    })
+                    .collect();
+                Preview {
+                    text: truncate(&normalize(&names.join(", ")), width),
+                    lines: names.len().max(1),
+                }
+            }
+            _ => Preview {
+                text: truncate(&normalize(&item.content), width),
+                lines: item.content.trim().lines().count().max(1),
+            },
+        }
+    }
+}
+
+// Deja el texto en una sola línea: los saltos de línea y los tabuladores seguidos se
+// reemplazan por una marca y se quitan los caracteres de control, incluidos los que
+// cambian la dirección del texto.
+pub fn normalize(content: &str) -> String {
+    let mut line = String::with_capacity(content.len());
+    let mut pending: Option<&str> = None;
+    for c in content.trim().chars() {
+        match c {
+            '\n' | '\r' | '\u{0b}' | '\u{0c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => {
+                pending = Some(NEWLINE_MARKER);
+            }
+            // Un tabulador junto a un salto de línea no agrega nada.
+            '\t' => {
+                pending.get_or_insert(TAB_MARKER);
+            }
+            c if c.is_control() || is_bidi_control(c) => {}
+            c => {
+                if let Some(marker) = pending.take() {
+                    line.truncate(line.trim_end().len());
+                    line.push_str(marker);
+                }
+                // Los espacios después de una marca sobran.
+                if c.is_whitespace() && (line.ends_with(NEWLINE_MARKER) || line.ends_with(TAB_MARKER)) {
+                    continue;
+                }
+                line.push(c);
+            }
+        }
+    }
+    line
+}
+
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+// Corta por grafemas para no separar acentos combinados ni emojis compuestos, y mide
+// el ancho en columnas de terminal: los caracteres de Asia oriental ocupan dos.
+pub fn truncate(line: &str, width: usize) -> String {
+    if line.width() <= width {
+        return line.to_string();
+    }
+    let mut short = String::new();
+    let mut used = 0;
+    for grapheme in line.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width + 1 > width {
+            break;
+        }
+        used += grapheme_width;
+        short.push_str(grapheme);
+    }
+    short.truncate(short.trim_end().len());
+    short.push(ELLIPSIS);
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_by_grapheme_and_width() {
+        assert_eq!(truncate("canción", 10), "canción");
+        // "ó" escrito como "o" + acento combinado no se separa.
+        assert_eq!(truncate("cancio\u{301}n de cuna", 8), "cancio\u{301}n…");
+        assert_eq!(truncate("日本語のテキスト", 7), "日本語…");
+        assert_eq!(truncate("👩‍👩‍👧 familia", 4), "👩‍👩‍👧…");
+        assert_eq!(truncate("abc", 0), "…");
+    }
+
+    #[test]
+    fn normalizes_whitespace_and_control_characters() {
+        assert_eq!(normalize("  uno\r\n\r\n  dos\tcuatro\n"), "uno ⏎ dos ⇥ cuatro");
+        assert_eq!(normalize("a\t\n\tb"), "a ⏎ b");
+        assert_eq!(normalize("rojo\u{1b}[31m\u{7}\u{202e}txt"), "rojo[31mtxt");
+    }
+
+    #[test]
+    fn counts_lines() {
+        let preview = Preview::new(&ClipboardItem::test_text("\nselect *\nfrom clipboard\n\n"), 80);
+        assert_eq!(preview.text, "select * ⏎ from clipboard");
+        assert_eq!(preview.lines, 2);
+        assert_eq!(Preview::new(&ClipboardItem::test_text("uno"), 80).lines, 1);
+    }
+}
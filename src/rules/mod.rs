@@ -0,0 +1,311 @@
+use std::error::Error;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::db::NewClipboardItem;
+
+// Reglas que decide el daemon antes de guardar un contenido copiado.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub apps: AppRules,
+    pub content: ContentRules,
+}
+
+impl Rules {
+    pub fn compile(&self) -> Result<RuleSet, Box<dyn Error>> {
+        Ok(RuleSet {
+            apps: self.apps.clone(),
+            content: self.content.compile()?,
+        })
+    }
+}
+
+// Aplicaciones cuyo contenido nunca se guarda (`ignore`) o, si `allow` no está
+// vacía, las únicas cuyo contenido se guarda.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppRules {
+    pub ignore: Vec<String>,
+    pub allow: Vec<String>,
+}
+
+impl Default for AppRules {
+    fn default() -> Self {
+        AppRules {
+            ignore: [
+                // Gestores de contraseñas
+                "keepassxc",
+                "keepass2",
+                "bitwarden",
+                "1password",
+                "enpass",
+                "seahorse",
+                "gcr-prompter",
+                // Escritorio remoto
+                "remmina",
+                "vncviewer",
+                "xfreerdp",
+                "wlfreerdp",
+                "anydesk",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+impl AppRules {
+    // Con una lista `allow` el contenido de origen desconocido tampoco se guarda.
+    pub fn allows(&self, app: Option<&str>) -> bool {
+        match app {
+            Some(app) if self.ignore.iter().any(|rule| app_matches(rule, app)) => false,
+            Some(app) if !self.allow.is_empty() => {
+                self.allow.iter().any(|rule| app_matches(rule, app))
+            }
+            Some(_) => true,
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+// Compara sin distinguir mayúsculas con el WM_CLASS o el app-id completo, o con
+// el último segmento de un app-id como "org.keepassxc.KeePassXC".
+fn app_matches(rule: &str, app: &str) -> bool {
+    app.eq_ignore_ascii_case(rule)
+        || app
+            .rsplit('.')
+            .next()
+            .is_some_and(|name| name.eq_ignore_ascii_case(rule))
+}
+
+// Reglas sobre el contenido copiado. Los tipos MIME admiten comodines como "image/*".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentRules {
+    pub patterns: Vec<String>,
+    pub min_size: Option<usize>,
+    pub max_size: Option<usize>,
+    pub ignore_whitespace: bool,
+    pub allow_mime: Vec<String>,
+    pub deny_mime: Vec<String>,
+}
+
+impl Default for ContentRules {
+    fn default() -> Self {
+        ContentRules {
+            patterns: Vec::new(),
+            min_size: None,
+            max_size: None,
+            ignore_whitespace: true,
+            allow_mime: Vec::new(),
+            deny_mime: Vec::new(),
+        }
+    }
+}
+
+impl ContentRules {
+    pub fn compile(&self) -> Result<Vec<ContentRule>, Box<dyn Error>> {
+        let mut rules = Vec::new();
+        for pattern in &self.patterns {
+            let regex = Regex::new(pattern)
+                .map_err(|err| format!("invalid ignore pattern {pattern:?}: {err}"))?;
+            rules.push(ContentRule::Pattern(regex));
+        }
+        if let Some(size) = self.min_size {
+            rules.push(ContentRule::MinSize(size));
+        }
+        if let Some(size) = self.max_size {
+            rules.push(ContentRule::MaxSize(size));
+        }
+        if self.ignore_whitespace {
+            rules.push(ContentRule::WhitespaceOnly);
+        }
+        if !self.allow_mime.is_empty() {
+            rules.push(ContentRule::AllowMime(self.allow_mime.clone()));
+        }
+        if !self.deny_mime.is_empty() {
+            rules.push(ContentRule::DenyMime(self.deny_mime.clone()));
+        }
+        Ok(rules)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ContentRule {
+    Pattern(Regex),
+    MinSize(usize),
+    MaxSize(usize),
+    WhitespaceOnly,
+    AllowMime(Vec<String>),
+    DenyMime(Vec<String>),
+}
+
+impl ContentRule {
+    pub fn discards(&self, item: &NewClipboardItem) -> bool {
+        match self {
+            ContentRule::Pattern(regex) => item.data.is_none() && regex.is_match(item.content),
+            ContentRule::MinSize(size) => item.size() < *size,
+            ContentRule::MaxSize(size) => item.size() > *size,
+            ContentRule::WhitespaceOnly => item.data.is_none() && item.content.trim().is_empty(),
+            ContentRule::AllowMime(mimes) => !mimes.iter().any(|mime| mime_matches(mime, item.mime)),
+            ContentRule::DenyMime(mimes) => mimes.iter().any(|mime| mime_matches(mime, item.mime)),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ContentRule::Pattern(regex) => format!("pattern {:?}", regex.as_str()),
+            ContentRule::MinSize(size) => format!("min size {size} bytes"),
+            ContentRule::MaxSize(size) => format!("max size {size} bytes"),
+            ContentRule::WhitespaceOnly => "whitespace only".to_string(),
+            ContentRule::AllowMime(mimes) => format!("allowed MIME types {mimes:?}"),
+            ContentRule::DenyMime(mimes) => format!("denied MIME types {mimes:?}"),
+        }
+    }
+}
+
+fn mime_matches(rule: &str, mime: &str) -> bool {
+    let mime = mime.split(';').next().unwrap_or_default().trim();
+    match rule.strip_suffix("/*") {
+        Some(prefix) => mime
+            .split('/')
+            .next()
+            .is_some_and(|kind| kind.eq_ignore_ascii_case(prefix)),
+        None => mime.eq_ignore_ascii_case(rule),
+    }
+}
+
+// Reglas listas para evaluar cada contenido copiado.
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    apps: AppRules,
+    content: Vec<ContentRule>,
+}
+
+impl RuleSet {
+    // Devuelve la regla que descarta el contenido, si alguna lo hace.
+    pub fn discarded_by(&self, item: &NewClipboardItem) -> Option<String> {
+        if !self.apps.allows(item.source_app) {
+            return Some(format!(
+                "app rules for {}",
+                item.source_app.unwrap_or("an unknown application")
+            ));
+        }
+        self.content
+            .iter()
+            .find(|rule| rule.discards(item))
+            .map(ContentRule::describe)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(data: &[u8]) -> NewClipboardItem<'_> {
+        NewClipboardItem {
+            content_type: "image",
+            mime: "image/png",
+            content: "",
+            data: Some(data),
+            source_app: None,
+            secret: false,
+        }
+    }
+
+    #[test]
+    fn app_rules() {
+        let rules = AppRules::default();
+        assert!(!rules.allows(Some("KeePassXC")));
+        assert!(!rules.allows(Some("org.keepassxc.KeePassXC")));
+        assert!(rules.allows(Some("firefox")));
+        assert!(rules.allows(None));
+
+        let rules = AppRules {
+            ignore: Vec::new(),
+            allow: vec!["code".to_string()],
+        };
+        assert!(rules.allows(Some("Code")));
+        assert!(!rules.allows(Some("firefox")));
+        assert!(!rules.allows(None));
+    }
+
+    #[test]
+    fn pattern() {
+        let rule = ContentRule::Pattern(Regex::new(r"TICKET-\d+").unwrap());
+        assert!(rule.discards(&NewClipboardItem::test_text("see TICKET-1234")));
+        assert!(!rule.discards(&NewClipboardItem::test_text("see TICKET-abc")));
+        assert!(!rule.discards(&image(b"TICKET-1")));
+    }
+
+    #[test]
+    fn sizes() {
+        assert!(ContentRule::MinSize(3).discards(&NewClipboardItem::test_text("ab")));
+        assert!(!ContentRule::MinSize(3).discards(&NewClipboardItem::test_text("abc")));
+        assert!(ContentRule::MaxSize(3).discards(&NewClipboardItem::test_text("abcd")));
+        assert!(!ContentRule::MaxSize(3).discards(&NewClipboardItem::test_text("ñb")));
+        assert!(ContentRule::MaxSize(3).discards(&image(&[0; 4])));
+    }
+
+    #[test]
+    fn whitespace_only() {
+        assert!(ContentRule::WhitespaceOnly.discards(&NewClipboardItem::test_text("")));
+        assert!(ContentRule::WhitespaceOnly.discards(&NewClipboardItem::test_text(" \n\t")));
+        assert!(!ContentRule::WhitespaceOnly.discards(&NewClipboardItem::test_text(" a ")));
+        assert!(!ContentRule::WhitespaceOnly.discards(&image(&[1])));
+    }
+
+    #[test]
+    fn mime() {
+        let allow = ContentRule::AllowMime(vec!["text/*".to_string()]);
+        assert!(!allow.discards(&NewClipboardItem::test_text("a")));
+        assert!(allow.discards(&image(&[1])));
+
+        let deny = ContentRule::DenyMime(vec!["image/png".to_string()]);
+        assert!(deny.discards(&image(&[1])));
+        assert!(!deny.discards(&NewClipboardItem::test_text("a")));
+    }
+
+    #[test]
+    fn first_matching_rule_is_reported() {
+        let rules = Rules {
+            content: ContentRules {
+                patterns: vec!["secret".to_string()],
+                max_size: Some(10),
+                ..ContentRules::default()
+            },
+            ..Rules::default()
+        }
+        .compile()
+        .unwrap();
+        assert_eq!(rules.discarded_by(&NewClipboardItem::test_text("ok")), None);
+        assert_eq!(
+            rules.discarded_by(&NewClipboardItem::test_text("my secret")).as_deref(),
+            Some("pattern \"secret\"")
+        );
+        assert_eq!(
+            rules.discarded_by(&NewClipboardItem::test_text("a long enough text")).as_deref(),
+            Some("max size 10 bytes")
+        );
+
+        let mut item = NewClipboardItem::test_text("from keepassxc");
+        item.source_app = Some("keepassxc");
+        assert_eq!(
+            rules.discarded_by(&item).as_deref(),
+            Some("app rules for keepassxc")
+        );
+
+        assert!(Rules {
+            content: ContentRules {
+                patterns: vec!["(".to_string()],
+                ..ContentRules::default()
+            },
+            ..Rules::default()
+        }
+        .compile()
+        .is_err());
+    }
+}
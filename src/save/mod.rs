@@ -0,0 +1,323 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Cursor, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use directories_next::UserDirs;
+use image::DynamicImage;
+
+use crate::db::{self, ClipboardItem};
+use crate::kind::Kind;
+
+// Formatos en los que se puede guardar una imagen. En la base de datos siempre es PNG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Webp];
+
+    pub fn parse(name: &str) -> Option<ImageFormat> {
+        match name.to_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<ImageFormat> {
+        path.extension().and_then(|extension| ImageFormat::parse(&extension.to_string_lossy()))
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    fn encoding(self) -> image::ImageFormat {
+        match self {
+            ImageFormat::Png => image::ImageFormat::Png,
+            ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+            ImageFormat::Webp => image::ImageFormat::WebP,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Webp => "WebP",
+        })
+    }
+}
+
+// Carpeta donde se guarda si no se indica otra: Descargas o, si no hay, la carpeta personal.
+pub fn default_dir() -> PathBuf {
+    UserDirs::new()
+        .and_then(|dirs| dirs.download_dir().or(Some(dirs.home_dir())).map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+// "ropias-20240131-235959-json.json": la fecha en que se copió y lo que parece ser.
+pub fn default_name(item: &ClipboardItem, format: Option<ImageFormat>) -> String {
+    let timestamp: String = item
+        .created_at
+        .chars()
+        .filter_map(|c| match c {
+            '0'..='9' => Some(c),
+            ' ' => Some('-'),
+            _ => None,
+        })
+        .collect();
+    let (label, extension) = match item.content_type.as_str() {
+        "image" => ("image", format.unwrap_or(ImageFormat::Png).extension()),
+        "files" => ("files", ""),
+        _ => match item.kind {
+            Some(Kind::Code) => ("code", item.language.as_deref().map_or("txt", code_extension)),
+            Some(Kind::Json) => ("json", "json"),
+            Some(Kind::Xml) => ("xml", "xml"),
+            Some(kind) => (kind.as_str(), "txt"),
+            None => ("text", "txt"),
+        },
+    };
+    if extension.is_empty() {
+        format!("ropias-{timestamp}-{label}")
+    } else {
+        format!("ropias-{timestamp}-{label}.{extension}")
+    }
+}
+
+fn code_extension(language: &str) -> &'static str {
+    match language {
+        "rust" => "rs",
+        "python" => "py",
+        "javascript" => "js",
+        "typescript" => "ts",
+        "go" => "go",
+        "c" => "c",
+        "java" => "java",
+        "sql" => "sql",
+        "shell" => "sh",
+        "html" => "html",
+        "css" => "css",
+        _ => "txt",
+    }
+}
+
+// Guarda un elemento y devuelve lo que se escribió. `target` puede ser una carpeta
+// o un archivo; sin él se usa `default_dir`. Nunca se sobrescribe: si el nombre ya
+// existe se le agrega un número. Las listas de archivos se copian a una carpeta.
+pub fn save(item: &ClipboardItem, target: Option<&Path>, format: Option<ImageFormat>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if item.secret {
+        return Err(format!("clipboard item {} is a secret and is not saved to disk", item.id).into());
+    }
+    let target = target.map_or_else(default_dir, Path::to_path_buf);
+    if item.content_type == "files" {
+        return copy_files(item, &target);
+    }
+    let (path, format) = target_path(item, &target, format);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = match item.content_type.as_str() {
+        "image" => {
+            let data = db::get_clipboard_data(item.id)?.ok_or_else(|| format!("clipboard item {} has no image", item.id))?;
+            encode(&data, format.unwrap_or(ImageFormat::Png))?
+        }
+        _ => item.content.as_bytes().to_vec(),
+    };
+    let (path, mut file) = create_free(&path, create_file)?;
+    file.write_all(&contents)?;
+    Ok(vec![path])
+}
+
+// Archivo donde se guarda y formato de la imagen. Si `--format` no coincide con la
+// extensión se cambia la extensión, como al elegir el formato en la GUI.
+fn target_path(item: &ClipboardItem, target: &Path, format: Option<ImageFormat>) -> (PathBuf, Option<ImageFormat>) {
+    if target.is_dir() {
+        return (target.join(default_name(item, format)), format);
+    }
+    if item.content_type != "image" {
+        return (target.to_path_buf(), format);
+    }
+    match (format, ImageFormat::from_path(target)) {
+        (Some(format), Some(current)) if format == current => (target.to_path_buf(), Some(format)),
+        (Some(format), _) => (target.with_extension(format.extension()), Some(format)),
+        (None, current) => (target.to_path_buf(), current),
+    }
+}
+
+fn encode(png: &[u8], format: ImageFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    if format == ImageFormat::Png {
+        return Ok(png.to_vec());
+    }
+    let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)?;
+    // JPEG no tiene transparencia.
+    let image = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => DynamicImage::ImageRgba8(image.to_rgba8()),
+    };
+    let mut encoded = Vec::new();
+    image.write_to(&mut Cursor::new(&mut encoded), format.encoding())?;
+    Ok(encoded)
+}
+
+fn copy_files(item: &ClipboardItem, dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if dir.is_file() {
+        return Err(format!("{} is a file; file lists are copied into a folder", dir.display()).into());
+    }
+    fs::create_dir_all(dir)?;
+    let mut copied = Vec::new();
+    for source in item.content.lines().map(Path::new) {
+        let name = source.file_name().ok_or_else(|| format!("cannot copy {}", source.display()))?;
+        let destination = copy_into(source, &dir.join(name)).map_err(|err| format!("cannot copy {}: {err}", source.display()))?;
+        copied.push(destination);
+    }
+    Ok(copied)
+}
+
+// Copia a un nombre libre parecido a `destination`.
+fn copy_into(source: &Path, destination: &Path) -> io::Result<PathBuf> {
+    if source.is_dir() {
+        let (destination, ()) = create_free(destination, |path| fs::create_dir(path))?;
+        copy_children(source, &destination)?;
+        Ok(destination)
+    } else {
+        let (destination, mut file) = create_free(destination, create_file)?;
+        io::copy(&mut File::open(source)?, &mut file)?;
+        file.set_permissions(fs::metadata(source)?.permissions())?;
+        Ok(destination)
+    }
+}
+
+// Dentro de una carpeta recién creada no hay nada que sobrescribir.
+fn copy_children(source: &Path, destination: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let (source, destination) = (entry.path(), destination.join(entry.file_name()));
+        if source.is_dir() {
+            fs::create_dir(&destination)?;
+            copy_children(&source, &destination)?;
+        } else {
+            fs::copy(&source, &destination)?;
+        }
+    }
+    Ok(())
+}
+
+fn create_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+// "foto.png" → "foto-1.png", "foto-2.png"... hasta que `create` logre crearlo. Se
+// crea en el mismo paso en que se comprueba, para no pisar lo que otro proceso
+// haya creado entre medio.
+fn create_free<T>(path: &Path, create: impl Fn(&Path) -> io::Result<T>) -> io::Result<(PathBuf, T)> {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy()));
+    let candidates = (1..).map(|n| path.with_file_name(format!("{stem}-{n}{}", extension.as_deref().unwrap_or_default())));
+    for candidate in std::iter::once(path.to_path_buf()).chain(candidates) {
+        match create(&candidate) {
+            Ok(created) => return Ok((candidate, created)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ropias-save-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn image() -> ClipboardItem {
+        ClipboardItem {
+            content: String::new(),
+            content_type: "image".to_string(),
+            mime: "image/png".to_string(),
+            kind: None,
+            language: None,
+            ..ClipboardItem::test_text("")
+        }
+    }
+
+    #[test]
+    fn default_names() {
+        let json = ClipboardItem::test_text("{\"a\": 1}");
+        assert_eq!(default_name(&json, None), "ropias-20240101-000000-json.json");
+        assert_eq!(default_name(&image(), Some(ImageFormat::Jpeg)), "ropias-20240101-000000-image.jpg");
+        let files = ClipboardItem {
+            content_type: "files".to_string(),
+            ..ClipboardItem::test_text("/tmp/a")
+        };
+        assert_eq!(default_name(&files, None), "ropias-20240101-000000-files");
+    }
+
+    #[test]
+    fn image_formats() {
+        let path = |target: &str, format| target_path(&image(), Path::new(target), format);
+        assert_eq!(path("shot.png", Some(ImageFormat::Webp)), (PathBuf::from("shot.webp"), Some(ImageFormat::Webp)));
+        assert_eq!(path("shot.jpeg", Some(ImageFormat::Jpeg)), (PathBuf::from("shot.jpeg"), Some(ImageFormat::Jpeg)));
+        assert_eq!(path("shot", Some(ImageFormat::Png)), (PathBuf::from("shot.png"), Some(ImageFormat::Png)));
+        assert_eq!(path("shot.webp", None), (PathBuf::from("shot.webp"), Some(ImageFormat::Webp)));
+        let text = target_path(&ClipboardItem::test_text("a"), Path::new("notes.png"), Some(ImageFormat::Jpeg));
+        assert_eq!(text.0, PathBuf::from("notes.png"));
+    }
+
+    #[test]
+    fn free_names() {
+        let dir = temp_dir("free");
+        let path = dir.join("foto.png");
+        let created: Vec<_> = (0..3).map(|_| create_free(&path, create_file).unwrap().0).collect();
+        assert_eq!(created, [path.clone(), dir.join("foto-1.png"), dir.join("foto-2.png")]);
+        assert_eq!(create_free(&dir.join("docs"), |path| fs::create_dir(path)).unwrap().0, dir.join("docs"));
+        assert_eq!(create_free(&dir.join("docs"), |path| fs::create_dir(path)).unwrap().0, dir.join("docs-1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saves_without_overwriting() {
+        let dir = temp_dir("save");
+        let item = ClipboardItem::test_text("select 1");
+        let first = save(&item, Some(&dir.join("query.sql")), None).unwrap();
+        let second = save(&item, Some(&dir.join("query.sql")), None).unwrap();
+        assert_eq!(first, [dir.join("query.sql")]);
+        assert_eq!(second, [dir.join("query-1.sql")]);
+        assert_eq!(fs::read_to_string(&second[0]).unwrap(), "select 1");
+
+        let secret = ClipboardItem { secret: true, ..item };
+        assert!(save(&secret, Some(&dir), None).is_err());
+
+        let source = dir.join("source");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("nested/a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let files = ClipboardItem {
+            content_type: "files".to_string(),
+            ..ClipboardItem::test_text(&format!("{}\n{}", source.display(), dir.join("b.txt").display()))
+        };
+        let copied = save(&files, Some(&dir.join("out")), None).unwrap();
+        assert_eq!(copied, [dir.join("out/source"), dir.join("out/b.txt")]);
+        assert_eq!(fs::read_to_string(dir.join("out/source/nested/a.txt")).unwrap(), "a");
+        let again = save(&files, Some(&dir.join("out")), None).unwrap();
+        assert_eq!(again, [dir.join("out/source-1"), dir.join("out/b-1.txt")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::Path;
+
+use arboard::ImageData;
+use image::{ImageFormat, RgbaImage};
+
+use crate::config::{CaptureConfig, Config, RetentionConfig, TrashConfig};
+use crate::db::{self, NewClipboardItem};
+use crate::ipc::Event;
+use crate::rules::RuleSet;
+use crate::secrets::{SecretAction, SecretDetector};
+use crate::server::State;
+
+// La configuración del daemon, lista para evaluar cada contenido copiado.
+pub struct Settings {
+    pub capture: CaptureConfig,
+    retention: RetentionConfig,
+    trash: TrashConfig,
+    rules: RuleSet,
+    secrets: SecretDetector,
+    secret_action: SecretAction,
+}
+
+impl Settings {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn Error>> {
+        Ok(Settings {
+            capture: config.capture.clone(),
+            retention: config.retention.clone(),
+            trash: config.trash.clone(),
+            rules: config.rules.compile()?,
+            secrets: SecretDetector::new(&config.secrets.patterns)?,
+            secret_action: config.secrets.action,
+        })
+    }
+}
+
+// Si no se guardan archivos, una lista de rutas se guarda como texto.
+pub fn text(state: &mut State, text: &str, source_app: Option<&str>, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    match file_list(text).filter(|_| settings.capture.files) {
+        Some(paths) => save(state, NewClipboardItem {
+            content_type: "files",
+            mime: "text/uri-list",
+            content: &paths,
+            data: None,
+            source_app,
+            secret: false,
+        }, settings),
+        None if settings.capture.text => save(state, NewClipboardItem {
+            content_type: "text",
+            mime: "text/plain",
+            content: text,
+            data: None,
+            source_app,
+            secret: false,
+        }, settings),
+        _ => Ok(()),
+    }
+}
+
+// Las imágenes se guardan como PNG.
+pub fn image(state: &mut State, image: &ImageData, source_app: Option<&str>, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let rgba = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.to_vec())
+        .ok_or("clipboard image has an invalid size")?;
+    let mut png = Vec::new();
+    rgba.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    save(state, NewClipboardItem {
+        content_type: "image",
+        mime: "image/png",
+        content: "",
+        data: Some(&png),
+        source_app,
+        secret: false,
+    }, settings)
+}
+
+pub fn image_hash(image: &ImageData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (image.width, image.height, &image.bytes[..]).hash(&mut hasher);
+    hasher.finish()
+}
+
+// Guarda el contenido copiado si las reglas lo permiten.
+fn save(state: &mut State, mut item: NewClipboardItem, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    if let Some(rule) = settings.rules.discarded_by(&item) {
+        tracing::info!("discarded {} ({} bytes) by {rule}", item.mime, item.size());
+        return Ok(());
+    }
+    if item.data.is_none() && settings.secret_action != SecretAction::Store && settings.secrets.is_secret(item.content) {
+        if settings.secret_action == SecretAction::Ignore {
+            tracing::info!("discarded {} ({} bytes) by secret detection", item.mime, item.size());
+            return Ok(());
+        }
+        item.secret = true;
+    }
+    let id = db::save_clipboard_item(&item)?;
+    if let Some(item) = db::get_clipboard_item(id)? {
+        state.notify(Event::Added { item });
+    }
+    apply_retention(state, settings)
+}
+
+pub fn apply_retention(state: &mut State, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    let retention = &settings.retention;
+    for id in db::apply_retention(retention.max_items, retention.max_age_days, &retention.expire_tags)? {
+        state.notify(Event::Removed { id });
+    }
+    // Lo que está en la papelera ya no se ve, así que no hay nada que notificar.
+    if settings.trash.purge_after_days > 0 {
+        db::empty_trash(Some(settings.trash.purge_after_days))?;
+    }
+    Ok(())
+}
+
+// Los gestores de archivos copian una lista de URIs file:// o de rutas absolutas,
+// una por línea. Se guardan como rutas.
+pub fn file_list(text: &str) -> Option<String> {
+    let paths = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix("file://") {
+            Some(uri) => percent_decode(uri),
+            None if line.starts_with('/') && Path::new(line).exists() => Some(line.to_string()),
+            None => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!paths.is_empty()).then(|| paths.join("\n"))
+}
+
+fn percent_decode(uri: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut rest = uri.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
@@ -0,0 +1,304 @@
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use async_std::task::block_on;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+use zbus::object_server::SignalContext;
+use zbus::{fdo, interface};
+
+use crate::db::{self, ClearScope, ClipboardItem};
+use crate::ipc::Event;
+use crate::preview::SECRET_MASK;
+use crate::server::SharedState;
+
+pub const NAME: &str = "io.github.ruby232.Ropias";
+pub const PATH: &str = "/io/github/ruby232/Ropias";
+
+// (id, contenido, fecha de creación, favorito)
+type DbusItem = (i32, String, String, bool);
+
+struct Service {
+    state: SharedState,
+}
+
+fn failed(err: Box<dyn Error>) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+fn to_dbus(items: Vec<ClipboardItem>) -> Vec<DbusItem> {
+    items
+        .into_iter()
+        .map(|item| (item.id, public_content(&item).to_string(), item.created_at, item.favorite))
+        .collect()
+}
+
+// Cualquier proceso puede leer el bus de la sesión, así que los secretos viajan
+// ocultos, como se muestran en la GUI y la CLI.
+fn public_content(item: &ClipboardItem) -> &str {
+    match item.secret {
+        true => SECRET_MASK,
+        false => &item.content,
+    }
+}
+
+// Un límite de 0 devuelve todo el historial.
+fn limit(limit: u32) -> Option<usize> {
+    (limit > 0).then_some(limit as usize)
+}
+
+#[interface(name = "io.github.ruby232.Ropias1")]
+impl Service {
+    fn list(&self, limit: u32) -> fdo::Result<Vec<DbusItem>> {
+        db::list_clipboard_content(self::limit(limit))
+            .map(to_dbus)
+            .map_err(failed)
+    }
+
+    fn search(&self, query: &str, limit: u32) -> fdo::Result<Vec<DbusItem>> {
+        db::search_clipboard_content(query, self::limit(limit))
+            .map(to_dbus)
+            .map_err(failed)
+    }
+
+    fn copy_by_id(&self, id: i32) -> fdo::Result<()> {
+        self.state.lock().unwrap().set_clipboard(id, false).map_err(failed)
+    }
+
+    fn delete(&self, id: i32) -> fdo::Result<()> {
+        self.state.lock().unwrap().delete(id).map_err(failed)
+    }
+
+    fn clear(&self) -> fdo::Result<u32> {
+        let removed = self.state.lock().unwrap().clear(&ClearScope::default(), false).map_err(failed)?.len();
+        Ok(removed as u32)
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.state.lock().unwrap().pause(None).map_err(failed)
+    }
+
+    fn pause_for(&self, minutes: u32) -> fdo::Result<()> {
+        self.state.lock().unwrap().pause(Some(minutes.into())).map_err(failed)
+    }
+
+    fn resume(&self) {
+        self.state.lock().unwrap().resume();
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    // Segundos desde UNIX_EPOCH, 0 si la pausa no es temporal.
+    #[zbus(property)]
+    fn paused_until(&self) -> u64 {
+        self.state.lock().unwrap().status().paused_until.unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    fn incognito(&self) -> bool {
+        self.state.lock().unwrap().incognito
+    }
+
+    #[zbus(property)]
+    fn set_incognito(&mut self, incognito: bool) {
+        self.state.lock().unwrap().set_incognito(incognito);
+    }
+
+    #[zbus(property)]
+    fn item_count(&self) -> u32 {
+        db::count_clipboard_items().unwrap_or_default() as u32
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    #[zbus(signal)]
+    async fn item_added(ctxt: &SignalContext<'_>, id: i32, content: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn item_removed(ctxt: &SignalContext<'_>, id: i32) -> zbus::Result<()>;
+}
+
+// El servicio deja de estar publicado cuando se libera la conexión devuelta.
+pub fn listen(state: SharedState) -> Result<Connection, Box<dyn Error>> {
+    let connection = serve(Builder::session()?, state)?;
+    tracing::info!("D-Bus service registered as {NAME}");
+    Ok(connection)
+}
+
+fn serve(builder: Builder, state: SharedState) -> Result<Connection, Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    state.lock().unwrap().subscribe(sender);
+    let connection = builder
+        .name(NAME)?
+        .serve_at(PATH, Service { state })?
+        .build()?;
+
+    let signals = connection.clone();
+    thread::spawn(move || {
+        if let Err(err) = forward_events(&signals, receiver) {
+            tracing::warn!("stopped emitting D-Bus signals: {err}");
+        }
+    });
+    Ok(connection)
+}
+
+// Traduce los eventos del daemon a señales y cambios de propiedades.
+fn forward_events(connection: &Connection, receiver: Receiver<Event>) -> zbus::Result<()> {
+    let iface = connection.object_server().interface::<_, Service>(PATH)?;
+    let ctxt = iface.signal_context();
+    for event in receiver {
+        block_on(async {
+            match event {
+                Event::Added { item } => {
+                    Service::item_added(ctxt, item.id, public_content(&item)).await?;
+                    iface.get().item_count_changed(ctxt).await
+                }
+                Event::Removed { id } => {
+                    Service::item_removed(ctxt, id).await?;
+                    iface.get().item_count_changed(ctxt).await
+                }
+                Event::Updated { .. } => Ok(()),
+                Event::Status { .. } => {
+                    let iface = iface.get();
+                    iface.paused_changed(ctxt).await?;
+                    iface.paused_until_changed(ctxt).await?;
+                    iface.incognito_changed(ctxt).await
+                }
+            }
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+    use std::sync::{Arc, Mutex};
+
+    use zbus::blocking::Proxy;
+    use zbus::CacheProperties;
+
+    use super::*;
+    use crate::server::State;
+
+    const CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:tmpdir=/tmp</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+    #[test]
+    fn secrets_are_masked() {
+        let item = |id, secret| ClipboardItem {
+            id,
+            secret,
+            ..ClipboardItem::test_text("hunter2-Hunter2!")
+        };
+        let items = to_dbus(vec![item(1, true), item(2, false)]);
+        assert_eq!(items[0].1, SECRET_MASK);
+        assert_eq!(items[1].1, "hunter2-Hunter2!");
+    }
+
+    // Levanta un dbus-daemon privado para no depender del bus de la sesión.
+    #[test]
+    fn service_on_private_bus() {
+        let dir = env::temp_dir().join(format!("ropias-dbus-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bus.conf"), CONFIG).unwrap();
+        let _db = db::use_test_path(dir.join("clipboard.db"));
+
+        let mut bus = match Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", dir.join("bus.conf").display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(bus) => bus,
+            Err(err) => {
+                eprintln!("skipping D-Bus test, dbus-daemon not available: {err}");
+                return;
+            }
+        };
+        let mut address = String::new();
+        BufReader::new(bus.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+
+        let first = db::save_clipboard_content("hola mundo", None).unwrap();
+        let second = db::save_clipboard_content("adiós", Some("firefox")).unwrap();
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let _service = serve(Builder::address(address.trim()).unwrap(), state.clone()).unwrap();
+
+        let client = Builder::address(address.trim()).unwrap().build().unwrap();
+        let proxy = zbus::blocking::proxy::Builder::<Proxy>::new(&client)
+            .destination(NAME)
+            .unwrap()
+            .path(PATH)
+            .unwrap()
+            .interface("io.github.ruby232.Ropias1")
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .unwrap();
+
+        let items: Vec<DbusItem> = proxy.call("List", &(0u32,)).unwrap();
+        assert_eq!(items.len(), 2);
+        let found: Vec<DbusItem> = proxy.call("Search", &("mundo", 0u32)).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, first);
+        assert_eq!(proxy.get_property::<u32>("ItemCount").unwrap(), 2);
+
+        assert!(!proxy.get_property::<bool>("Paused").unwrap());
+        proxy.call_method("Pause", &()).unwrap();
+        assert!(proxy.get_property::<bool>("Paused").unwrap());
+        assert!(state.lock().unwrap().paused);
+        proxy.call_method("Resume", &()).unwrap();
+        assert!(!proxy.get_property::<bool>("Paused").unwrap());
+        proxy.call_method("PauseFor", &(5u32,)).unwrap();
+        assert!(proxy.get_property::<u64>("PausedUntil").unwrap() > 0);
+        proxy.call_method("Resume", &()).unwrap();
+        assert_eq!(proxy.get_property::<u64>("PausedUntil").unwrap(), 0);
+
+        proxy.set_property("Incognito", true).unwrap();
+        assert!(state.lock().unwrap().incognito);
+
+        let mut removed = proxy.receive_signal("ItemRemoved").unwrap();
+        proxy.call_method("Delete", &(second,)).unwrap();
+        let signal = removed.next().unwrap();
+        assert_eq!(signal.body().deserialize::<i32>().unwrap(), second);
+        assert!(proxy.call_method("Delete", &(second,)).is_err());
+
+        let mut added = proxy.receive_signal("ItemAdded").unwrap();
+        let item = db::get_clipboard_item(first).unwrap().unwrap();
+        state.lock().unwrap().notify(Event::Added { item });
+        let signal = added.next().unwrap();
+        let (id, content): (i32, String) = signal.body().deserialize().unwrap();
+        assert_eq!((id, content.as_str()), (first, "hola mundo"));
+
+        let cleared: u32 = proxy.call("Clear", &()).unwrap();
+        assert_eq!(cleared, 1);
+        assert_eq!(proxy.get_property::<u32>("ItemCount").unwrap(), 0);
+
+        bus.kill().unwrap();
+        bus.wait().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
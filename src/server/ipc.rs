@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::db;
+use crate::ipc::{
+    read_message, socket_path, write_message, Envelope, Request, Response,
+    PROTOCOL_VERSION,
+};
+use crate::server::SharedState;
+
+pub fn listen(state: SharedState) -> Result<(), Box<dyn Error>> {
+    let path = socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        return Err(format!("a daemon is already listening on {}", path.display()).into());
+    }
+    // Socket huérfano de una ejecución anterior.
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    tracing::info!("listening on {}", path.display());
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let state = state.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = handle_client(stream, state) {
+                            tracing::debug!("client disconnected: {err}");
+                        }
+                    });
+                }
+                Err(err) => tracing::warn!("failed to accept connection: {err}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_client(stream: UnixStream, state: SharedState) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    while let Some(envelope) = read_request(&mut reader) {
+        let request = match envelope {
+            Ok(Envelope { version, .. }) if version != PROTOCOL_VERSION => {
+                write_message(&mut writer, error(format!(
+                    "unsupported protocol version {version}, expected {PROTOCOL_VERSION}"
+                )))?;
+                continue;
+            }
+            Ok(envelope) => envelope.body,
+            Err(err) => {
+                write_message(&mut writer, error(format!("invalid request: {err}")))?;
+                continue;
+            }
+        };
+
+        if let Request::Subscribe = request {
+            let (sender, receiver) = mpsc::channel();
+            state.lock().unwrap().subscribe(sender);
+            write_message(&mut writer, Response::Done)?;
+            for event in receiver {
+                write_message(&mut writer, Response::Event { event })?;
+            }
+            return Ok(());
+        }
+
+        let response = handle_request(request, &state).unwrap_or_else(|err| error(err.to_string()));
+        write_message(&mut writer, response)?;
+    }
+    Ok(())
+}
+
+fn read_request(reader: &mut BufReader<UnixStream>) -> Option<io::Result<Envelope<Request>>> {
+    read_message(reader).transpose()
+}
+
+fn error(message: String) -> Response {
+    Response::Error { message }
+}
+
+fn handle_request(request: Request, state: &SharedState) -> Result<Response, Box<dyn Error>> {
+    let response = match request {
+        Request::List { limit } => Response::Items {
+            items: db::list_clipboard_content(limit)?,
+        },
+        Request::Search { query, limit } => Response::Items {
+            items: db::search_clipboard_content(&query, limit)?,
+        },
+        Request::Page { query } => Response::Items {
+            items: db::page_clipboard_content(&query)?,
+        },
+        Request::Get { id } => Response::Item {
+            item: db::get_clipboard_item(id)?,
+        },
+        Request::SetClipboard { id, plain } => {
+            state.lock().unwrap().set_clipboard(id, plain)?;
+            Response::Done
+        }
+        Request::Delete { id } => {
+            state.lock().unwrap().delete(id)?;
+            Response::Done
+        }
+        Request::Pin { id, pinned } => {
+            state.lock().unwrap().pin(id, pinned)?;
+            Response::Done
+        }
+        Request::Edit { id, content } => {
+            state.lock().unwrap().edit(id, &content)?;
+            Response::Done
+        }
+        Request::Tag { id, tag } => {
+            state.lock().unwrap().tag(id, &tag, true)?;
+            Response::Done
+        }
+        Request::Untag { id, tag } => {
+            state.lock().unwrap().tag(id, &tag, false)?;
+            Response::Done
+        }
+        Request::Tags => Response::Tags {
+            tags: db::list_tags()?,
+        },
+        Request::CopyText { content } => {
+            let item = state.lock().unwrap().copy_text(&content)?;
+            Response::Item { item: Some(item) }
+        }
+        Request::Clear { scope, secure } => Response::Ids {
+            ids: state.lock().unwrap().clear(&scope, secure)?,
+        },
+        Request::Trash => Response::Trash {
+            items: db::list_trash()?,
+        },
+        Request::Restore { ids } => Response::Ids {
+            ids: state.lock().unwrap().restore(&ids)?,
+        },
+        Request::EmptyTrash => {
+            db::empty_trash(None)?;
+            Response::Done
+        }
+        Request::Pause { minutes } => {
+            state.lock().unwrap().pause(minutes)?;
+            Response::Done
+        }
+        Request::Resume => {
+            state.lock().unwrap().resume();
+            Response::Done
+        }
+        Request::Incognito { enabled } => {
+            state.lock().unwrap().set_incognito(enabled);
+            Response::Done
+        }
+        Request::Status => Response::Status {
+            status: state.lock().unwrap().status(),
+        },
+        Request::Subscribe => unreachable!("subscriptions are handled by the connection loop"),
+    };
+    Ok(response)
+}
@@ -1,19 +1,344 @@
+pub mod capture;
+mod dbus;
+mod ipc;
+mod source;
+
 use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use arboard::Clipboard;
-use crate::db;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use arboard::{Clipboard, ImageData};
+use image::ImageFormat;
+use crate::db::{self, ClearScope, ClipboardItem};
+use crate::ipc::{Event, Status, PROTOCOL_VERSION};
+use crate::config::{Config, ConfigWatcher};
+use self::capture::Settings;
+use self::source::SourceDetector;
+
+// Estado compartido entre el bucle del portapapeles y los clientes del socket.
+#[derive(Default)]
+pub struct State {
+    pub last_content: String,
+    // Hash de la última imagen vista en el portapapeles.
+    pub last_image: Option<u64>,
+    pub paused: bool,
+    // Momento en que se reanuda una pausa temporal.
+    pub resume_at: Option<SystemTime>,
+    // En modo incógnito se sigue leyendo el portapapeles pero no se guarda nada.
+    pub incognito: bool,
+    subscribers: Vec<Sender<Event>>,
+}
+
+pub type SharedState = Arc<Mutex<State>>;
+
+impl State {
+    pub fn subscribe(&mut self, subscriber: Sender<Event>) {
+        self.subscribers.push(subscriber);
+    }
+
+    // Los suscriptores desconectados se descartan al fallar el envío.
+    pub fn notify(&mut self, event: Event) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn set_clipboard(&mut self, id: i32, plain: bool) -> Result<(), Box<dyn Error>> {
+        let item = db::get_clipboard_item(id)?.ok_or_else(|| not_found(id))?;
+        // Evita que el bucle del portapapeles vuelva a guardar lo mismo.
+        match write_clipboard(&item, plain)? {
+            Some(image) => {
+                self.last_image = Some(image);
+                self.last_content = String::new();
+            }
+            None => self.last_content = item.content,
+        }
+        Ok(())
+    }
+
+    pub fn delete(&mut self, id: i32) -> Result<(), Box<dyn Error>> {
+        if !db::delete_clipboard_item(id)? {
+            return Err(not_found(id));
+        }
+        self.notify(Event::Removed { id });
+        Ok(())
+    }
+
+    pub fn pin(&mut self, id: i32, pinned: bool) -> Result<(), Box<dyn Error>> {
+        if !db::set_favorite(id, pinned)? {
+            return Err(not_found(id));
+        }
+        if let Some(item) = db::get_clipboard_item(id)? {
+            self.notify(Event::Updated { item });
+        }
+        Ok(())
+    }
+
+    pub fn edit(&mut self, id: i32, content: &str) -> Result<(), Box<dyn Error>> {
+        if !db::update_clipboard_content(id, content)? {
+            return Err(format!("clipboard item {id} not found or not editable").into());
+        }
+        if let Some(item) = db::get_clipboard_item(id)? {
+            self.notify(Event::Updated { item });
+        }
+        Ok(())
+    }
+
+    pub fn tag(&mut self, id: i32, tag: &str, tagged: bool) -> Result<(), Box<dyn Error>> {
+        if tagged {
+            if !db::tag_item(id, tag)? {
+                return Err(not_found(id));
+            }
+        } else if !db::untag_item(id, tag)? {
+            return Err(format!("clipboard item {id} is not tagged {tag:?}").into());
+        }
+        if let Some(item) = db::get_clipboard_item(id)? {
+            self.notify(Event::Updated { item });
+        }
+        Ok(())
+    }
+
+    // El resultado de una acción se guarda como un elemento nuevo y se copia.
+    pub fn copy_text(&mut self, content: &str) -> Result<ClipboardItem, Box<dyn Error>> {
+        let id = db::save_clipboard_content(content, Some(db::APP_NAME))?;
+        let item = db::get_clipboard_item(id)?.ok_or_else(|| not_found(id))?;
+        self.notify(Event::Added { item: item.clone() });
+        self.set_clipboard(id, false)?;
+        Ok(item)
+    }
+
+    pub fn clear(&mut self, scope: &ClearScope, secure: bool) -> Result<Vec<i32>, Box<dyn Error>> {
+        let ids = match secure {
+            true => db::wipe_clipboard_content(scope)?,
+            false => db::clear_clipboard_content(scope)?,
+        };
+        for &id in &ids {
+            self.notify(Event::Removed { id });
+        }
+        Ok(ids)
+    }
+
+    // Saca elementos de la papelera; falla si ninguno estaba en ella.
+    pub fn restore(&mut self, ids: &[i32]) -> Result<Vec<i32>, Box<dyn Error>> {
+        let restored = db::restore_clipboard_items(ids)?;
+        if restored.is_empty() && !ids.is_empty() {
+            return Err(not_in_trash(ids));
+        }
+        for &id in &restored {
+            if let Some(item) = db::get_clipboard_item(id)? {
+                self.notify(Event::Added { item });
+            }
+        }
+        Ok(restored)
+    }
 
-pub fn server() -> Result<(), Box<dyn Error>> {
+    // Sin `minutes` la pausa dura hasta `resume`. Los minutos vienen de los clientes,
+    // así que una pausa que no cabe en el reloj es un error y no un pánico.
+    pub fn pause(&mut self, minutes: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let resume_at = match minutes {
+            Some(minutes) => Some(
+                minutes
+                    .checked_mul(60)
+                    .and_then(|seconds| SystemTime::now().checked_add(Duration::from_secs(seconds)))
+                    .ok_or_else(|| format!("cannot pause for {minutes} minutes"))?,
+            ),
+            None => None,
+        };
+        self.paused = true;
+        self.resume_at = resume_at;
+        self.notify_status();
+        Ok(())
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.resume_at = None;
+        self.notify_status();
+    }
+
+    pub fn set_incognito(&mut self, incognito: bool) {
+        self.incognito = incognito;
+        self.notify_status();
+    }
+
+    fn resume_due(&self) -> bool {
+        self.resume_at.is_some_and(|resume_at| resume_at <= SystemTime::now())
+    }
+
+    fn notify_status(&mut self) {
+        let status = self.status();
+        self.notify(Event::Status { status });
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol: PROTOCOL_VERSION,
+            paused: self.paused,
+            paused_until: self.resume_at.and_then(|resume_at| {
+                resume_at.duration_since(UNIX_EPOCH).ok().map(|since| since.as_secs())
+            }),
+            incognito: self.incognito,
+            items: db::count_clipboard_items().unwrap_or_default(),
+        }
+    }
+}
+
+// Pone un elemento en el portapapeles. Las listas de archivos van como
+// text/uri-list, para pegarlas como archivos; con `plain` van solo sus rutas como
+// texto, y las imágenes no tienen texto. Devuelve el hash de la imagen copiada.
+pub fn write_clipboard(item: &ClipboardItem, plain: bool) -> Result<Option<u64>, Box<dyn Error>> {
     let mut clipboard = Clipboard::new()?;
-    let mut last_content = clipboard.get_text().unwrap_or_default();
+    match item.content_type.as_str() {
+        "image" if plain => Err(format!("clipboard item {} is an image and has no text", item.id).into()),
+        "image" => {
+            let data = db::get_clipboard_data(item.id)?.ok_or_else(|| not_found(item.id))?;
+            let rgba = image::load_from_memory_with_format(&data, ImageFormat::Png)?.to_rgba8();
+            let image = ImageData {
+                width: rgba.width() as usize,
+                height: rgba.height() as usize,
+                bytes: rgba.into_raw().into(),
+            };
+            let hash = capture::image_hash(&image);
+            clipboard.set_image(image)?;
+            Ok(Some(hash))
+        }
+        "files" if !plain => {
+            clipboard.set().file_list(&item.content.lines().collect::<Vec<_>>())?;
+            Ok(None)
+        }
+        _ => {
+            clipboard.set_text(item.content.clone())?;
+            Ok(None)
+        }
+    }
+}
+
+fn not_found(id: i32) -> Box<dyn Error> {
+    format!("clipboard item {id} not found").into()
+}
+
+pub fn not_in_trash(ids: &[i32]) -> Box<dyn Error> {
+    match ids {
+        [id] => format!("clipboard item {id} is not in the trash").into(),
+        _ => "none of those clipboard items is in the trash".into(),
+    }
+}
+
+// Transferir una imagen es costoso, así que se consulta con menos frecuencia que el texto.
+const IMAGE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Cada cuánto se revisa si cambió el archivo de configuración y se aplican los
+// límites de antigüedad del historial.
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn server(config: Config, config_path: PathBuf) -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
 
+    let mut settings = Settings::new(&config)?;
+    let mut watcher = ConfigWatcher::new(config_path);
+
+    let mut clipboard = Clipboard::new()?;
+    let state: SharedState = Arc::new(Mutex::new(State {
+        last_content: clipboard.get_text().unwrap_or_default(),
+        ..State::default()
+    }));
+    ipc::listen(state.clone())?;
+    // El daemon sigue funcionando sin D-Bus, por ejemplo fuera de una sesión gráfica.
+    let _dbus = dbus::listen(state.clone())
+        .inspect_err(|err| tracing::warn!("D-Bus service not available: {err}"))
+        .ok();
+
+    let sources = SourceDetector::new();
+    let mut was_paused = false;
+    let mut image_checked = Instant::now();
+    let mut config_checked = Instant::now();
+    let mut retention_applied = Instant::now();
     loop {
-        let current_content = clipboard.get_text().unwrap_or_default();
-        if current_content != last_content {
-            db::save_clipboard_content(&current_content)?;
-            last_content = current_content;
+        if config_checked.elapsed() >= CONFIG_POLL_INTERVAL {
+            config_checked = Instant::now();
+            match watcher.poll() {
+                Some(Ok(config)) => match Settings::new(&config) {
+                    Ok(new_settings) => {
+                        db::set_path(config.database_path());
+                        settings = new_settings;
+                        tracing::info!("configuration reloaded");
+                    }
+                    Err(err) => tracing::error!("{err}, keeping the previous configuration"),
+                },
+                Some(Err(err)) => tracing::error!("{err}\nkeeping the previous configuration"),
+                None => {}
+            }
+        }
+        {
+            let mut state = state.lock().unwrap();
+            if retention_applied.elapsed() >= RETENTION_INTERVAL {
+                retention_applied = Instant::now();
+                // Un error pasajero de la base, como SQLITE_BUSY, no detiene el daemon.
+                if let Err(err) = capture::apply_retention(&mut state, &settings) {
+                    tracing::warn!("cannot apply the retention limits: {err}");
+                }
+            }
+            if state.resume_due() {
+                tracing::info!("pause expired, resuming capture");
+                state.resume();
+            }
+            if state.paused {
+                was_paused = true;
+            } else {
+                // Lo copiado durante una pausa no se guarda al reanudar.
+                let record = !was_paused && !state.incognito;
+                let current_content = clipboard.get_text().unwrap_or_default();
+                if current_content != state.last_content {
+                    if record && !current_content.is_empty() {
+                        let source_app = sources.current_app();
+                        if let Err(err) = capture::text(&mut state, &current_content, source_app.as_deref(), &settings) {
+                            tracing::warn!("cannot save the copied text: {err}");
+                        }
+                    }
+                    state.last_content = current_content;
+                    state.last_image = None;
+                }
+                // Sin texto en el portapapeles puede haber una imagen.
+                if settings.capture.images
+                    && state.last_content.is_empty()
+                    && (was_paused || image_checked.elapsed() >= IMAGE_POLL_INTERVAL)
+                {
+                    image_checked = Instant::now();
+                    if let Ok(image) = clipboard.get_image() {
+                        let hash = capture::image_hash(&image);
+                        if state.last_image != Some(hash) {
+                            if record {
+                                let source_app = sources.current_app();
+                                if let Err(err) = capture::image(&mut state, &image, source_app.as_deref(), &settings) {
+                                    tracing::warn!("cannot save the copied image: {err}");
+                                }
+                            }
+                            state.last_image = Some(hash);
+                        }
+                    }
+                }
+                was_paused = false;
+            }
         }
         thread::sleep(Duration::from_millis(10));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_too_long() {
+        let mut state = State::default();
+        assert!(state.pause(Some(u64::MAX)).is_err());
+        assert!(!state.paused);
+        state.pause(Some(5)).unwrap();
+        assert!(state.paused && state.resume_at.is_some());
+        state.resume();
+        assert!(!state.paused && state.resume_at.is_none());
+    }
+}
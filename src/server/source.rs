@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry;
+use wayland_client::{event_created_child, Connection as WaylandConnection, Dispatch, Proxy, QueueHandle, WEnum};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
+use x11rb::connection::Connection as X11Connection;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window};
+use x11rb::rust_connection::RustConnection;
+use x11rb::NONE;
+
+// Intenta identificar la aplicación que copió el contenido actual del portapapeles.
+pub struct SourceDetector {
+    wayland: Option<Arc<Mutex<Option<String>>>>,
+    x11: Option<X11Source>,
+}
+
+impl SourceDetector {
+    pub fn new() -> Self {
+        // En Wayland las ventanas de XWayland no sirven para las aplicaciones nativas.
+        let wayland = env::var_os("WAYLAND_DISPLAY").and_then(|_| watch_wayland_toplevels());
+        let x11 = match wayland {
+            Some(_) => None,
+            None => X11Source::connect(),
+        };
+        if wayland.is_none() && x11.is_none() {
+            tracing::info!("source application detection is not available");
+        }
+        SourceDetector { wayland, x11 }
+    }
+
+    pub fn current_app(&self) -> Option<String> {
+        if let Some(active) = &self.wayland {
+            return active.lock().unwrap().clone();
+        }
+        self.x11.as_ref()?.current_app()
+    }
+}
+
+struct X11Source {
+    conn: RustConnection,
+    root: Window,
+    clipboard: Atom,
+    client_leader: Atom,
+    active_window: Atom,
+}
+
+impl X11Source {
+    fn connect() -> Option<Self> {
+        let (conn, screen) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots[screen].root;
+        let atom = |name: &str| Some(conn.intern_atom(false, name.as_bytes()).ok()?.reply().ok()?.atom);
+        Some(X11Source {
+            clipboard: atom("CLIPBOARD")?,
+            client_leader: atom("WM_CLIENT_LEADER")?,
+            active_window: atom("_NET_ACTIVE_WINDOW")?,
+            conn,
+            root,
+        })
+    }
+
+    // El dueño de la selección suele ser una ventana oculta sin WM_CLASS, así que se
+    // prueba con su líder y, como último recurso, con la ventana activa.
+    fn current_app(&self) -> Option<String> {
+        let owner = self.conn.get_selection_owner(self.clipboard).ok()?.reply().ok()?.owner;
+        self.wm_class(owner)
+            .or_else(|| self.wm_class(self.window_property(owner, self.client_leader)?))
+            .or_else(|| self.wm_class(self.window_property(self.root, self.active_window)?))
+    }
+
+    fn window_property(&self, window: Window, property: Atom) -> Option<Window> {
+        if window == NONE {
+            return None;
+        }
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let window = reply.value32()?.next()?;
+        (window != NONE).then_some(window)
+    }
+
+    // WM_CLASS contiene "instancia\0clase\0"; se usa la clase.
+    fn wm_class(&self, window: Window) -> Option<String> {
+        if window == NONE {
+            return None;
+        }
+        let reply = self
+            .conn
+            .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+            .ok()?
+            .reply()
+            .ok()?;
+        reply
+            .value
+            .split(|&byte| byte == 0)
+            .rfind(|part| !part.is_empty())
+            .map(|class| String::from_utf8_lossy(class).into_owned())
+    }
+}
+
+// Wayland no expone el origen de una selección; se toma como origen el app-id
+// de la ventana activa, según wlr-foreign-toplevel-management.
+fn watch_wayland_toplevels() -> Option<Arc<Mutex<Option<String>>>> {
+    let conn = WaylandConnection::connect_to_env().ok()?;
+    let (globals, mut queue) = registry_queue_init::<Toplevels>(&conn).ok()?;
+    let qh = queue.handle();
+    let _manager: ZwlrForeignToplevelManagerV1 = globals.bind(&qh, 1..=3, ()).ok()?;
+
+    let active = Arc::new(Mutex::new(None));
+    let mut toplevels = Toplevels {
+        toplevels: HashMap::new(),
+        active: active.clone(),
+    };
+    thread::spawn(move || loop {
+        if let Err(err) = queue.blocking_dispatch(&mut toplevels) {
+            tracing::warn!("lost Wayland toplevel information: {err}");
+            *toplevels.active.lock().unwrap() = None;
+            break;
+        }
+    });
+    Some(active)
+}
+
+#[derive(Default)]
+struct Toplevel {
+    app_id: Option<String>,
+    activated: bool,
+}
+
+struct Toplevels {
+    toplevels: HashMap<ObjectId, Toplevel>,
+    active: Arc<Mutex<Option<String>>>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Toplevels {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for Toplevels {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        _: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+
+    event_created_child!(Toplevels, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for Toplevels {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &WaylandConnection,
+        _: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::{Event, State};
+
+        let toplevel = state.toplevels.entry(handle.id()).or_default();
+        match event {
+            Event::AppId { app_id } => toplevel.app_id = Some(app_id),
+            Event::State { state: states } => {
+                toplevel.activated = states
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .any(|value| WEnum::from(value) == WEnum::Value(State::Activated));
+            }
+            Event::Done if toplevel.activated => {
+                *state.active.lock().unwrap() = toplevel.app_id.clone();
+            }
+            Event::Closed => {
+                if let Some(toplevel) = state.toplevels.remove(&handle.id()) {
+                    let mut active = state.active.lock().unwrap();
+                    if toplevel.activated && *active == toplevel.app_id {
+                        *active = None;
+                    }
+                }
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
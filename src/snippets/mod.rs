@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+// Una parte de un snippet: texto tal cual o un marcador que se completa al copiarlo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Date,
+    Time,
+    Clipboard,
+    Uuid,
+    // Se le pide al usuario; el texto es la etiqueta de la pregunta.
+    Input(String),
+}
+
+// El cuerpo de un snippet con sus marcadores: `{{date}}`, `{{time}}`,
+// `{{clipboard}}`, `{{uuid}}` e `{{input:Etiqueta}}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Part>);
+
+// Lo necesario para completar los marcadores.
+#[derive(Debug, Clone, Default)]
+pub struct Values {
+    // "AAAA-MM-DD HH:MM:SS" en hora local.
+    pub now: String,
+    pub clipboard: String,
+    pub inputs: HashMap<String, String>,
+}
+
+impl Template {
+    // Un `{{` sin cerrar queda como texto; un marcador desconocido es un error.
+    pub fn parse(body: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut rest = body;
+        while let Some((start, end)) = rest.find("{{").and_then(|start| Some((start, start + rest[start..].find("}}")?))) {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let name = rest[start + 2..end].trim();
+            parts.push(match name {
+                "date" => Part::Date,
+                "time" => Part::Time,
+                "clipboard" => Part::Clipboard,
+                "uuid" => Part::Uuid,
+                _ => match name.strip_prefix("input:").map(str::trim) {
+                    Some(label) if !label.is_empty() => Part::Input(label.to_string()),
+                    _ => {
+                        return Err(format!(
+                            "unknown placeholder {{{{{name}}}}}, expected date, time, clipboard, uuid or input:<label>"
+                        ))
+                    }
+                },
+            });
+            rest = &rest[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Template(parts))
+    }
+
+    // Las preguntas que hay que hacer, sin repetir y en orden de aparición.
+    pub fn inputs(&self) -> Vec<&str> {
+        let mut inputs = Vec::new();
+        for part in &self.0 {
+            if let Part::Input(label) = part {
+                if !inputs.contains(&label.as_str()) {
+                    inputs.push(label.as_str());
+                }
+            }
+        }
+        inputs
+    }
+
+    pub fn uses_clipboard(&self) -> bool {
+        self.0.contains(&Part::Clipboard)
+    }
+
+    // Cada `{{uuid}}` es uno nuevo.
+    pub fn expand(&self, values: &Values) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Date => values.now.get(..10).unwrap_or(&values.now).to_string(),
+                Part::Time => values.now.get(11..16).unwrap_or_default().to_string(),
+                Part::Clipboard => values.clipboard.clone(),
+                Part::Uuid => Uuid::new_v4().to_string(),
+                Part::Input(label) => values.inputs.get(label).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_placeholders() {
+        let template = Template::parse("{{input:Ticket}} {{ date }} {{time}}: {{clipboard}} ({{input:Ticket}})").unwrap();
+        assert_eq!(template.inputs(), ["Ticket"]);
+        assert!(template.uses_clipboard());
+        let values = Values {
+            now: "2024-01-31 23:59:59".to_string(),
+            clipboard: "fallo".to_string(),
+            inputs: HashMap::from([("Ticket".to_string(), "T-1".to_string())]),
+        };
+        assert_eq!(template.expand(&values), "T-1 2024-01-31 23:59: fallo (T-1)");
+    }
+
+    #[test]
+    fn uuids_are_new() {
+        let template = Template::parse("{{uuid}} {{uuid}}").unwrap();
+        let expanded = template.expand(&Values::default());
+        let (first, second) = expanded.split_once(' ').unwrap();
+        assert_eq!(first.len(), 36);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        assert!(Template::parse("{{fecha}}").is_err());
+        assert!(Template::parse("{{input:}}").is_err());
+        assert_eq!(Template::parse("a {{ b").unwrap(), Template(vec![Part::Text("a {{ b".to_string())]));
+    }
+}
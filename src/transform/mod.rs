@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+// Cambios que se aplican a un texto antes de volver a copiarlo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Trim,
+    Lower,
+    Upper,
+    Snake,
+    Camel,
+    StripAnsi,
+    JoinLines,
+    SortLines,
+    DedupeLines,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 9] = [
+        Transform::Trim,
+        Transform::Lower,
+        Transform::Upper,
+        Transform::Snake,
+        Transform::Camel,
+        Transform::StripAnsi,
+        Transform::JoinLines,
+        Transform::SortLines,
+        Transform::DedupeLines,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Trim => "trim",
+            Transform::Lower => "lower",
+            Transform::Upper => "upper",
+            Transform::Snake => "snake",
+            Transform::Camel => "camel",
+            Transform::StripAnsi => "strip-ansi",
+            Transform::JoinLines => "join",
+            Transform::SortLines => "sort",
+            Transform::DedupeLines => "dedupe",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Transform> {
+        Transform::ALL.into_iter().find(|transform| transform.name() == name)
+    }
+
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Transform::Trim => text.trim().to_string(),
+            Transform::Lower => text.to_lowercase(),
+            Transform::Upper => text.to_uppercase(),
+            Transform::Snake => map_lines(text, |line| words(line).join("_").to_lowercase()),
+            Transform::Camel => map_lines(text, camel_case),
+            Transform::StripAnsi => ANSI.replace_all(text, "").into_owned(),
+            Transform::JoinLines => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            Transform::SortLines => {
+                let mut lines: Vec<_> = text.lines().collect();
+                lines.sort_unstable();
+                lines.join("\n")
+            }
+            Transform::DedupeLines => {
+                let mut seen = HashSet::new();
+                text.lines()
+                    .filter(|line| seen.insert(*line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Secuencias CSI (colores, movimientos del cursor) y OSC (títulos, enlaces).
+static ANSI: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap());
+
+// Una lista de transformaciones que se aplican en orden.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pipeline(pub Vec<Transform>);
+
+impl Pipeline {
+    // Nombres separados por comas; cada uno es una transformación o un preset de la
+    // configuración, que se expande a sus pasos.
+    pub fn parse(spec: &str, presets: &BTreeMap<String, Vec<String>>) -> Result<Pipeline, String> {
+        let mut steps = Vec::new();
+        for name in spec.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            match (Transform::parse(name), presets.get(name)) {
+                (Some(transform), _) => steps.push(transform),
+                (None, Some(preset)) => {
+                    for step in preset {
+                        steps.push(Transform::parse(step).ok_or_else(|| unknown(step))?);
+                    }
+                }
+                (None, None) => return Err(unknown(name)),
+            }
+        }
+        if steps.is_empty() {
+            return Err("empty transform list".to_string());
+        }
+        Ok(Pipeline(steps))
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.0.iter().fold(text.to_string(), |text, transform| transform.apply(&text))
+    }
+}
+
+fn unknown(name: &str) -> String {
+    let names: Vec<_> = Transform::ALL.iter().map(|transform| transform.name()).collect();
+    format!("unknown transform {name:?}, expected a preset or one of {}", names.join(", "))
+}
+
+fn map_lines(text: &str, f: impl Fn(&str) -> String) -> String {
+    text.lines().map(f).collect::<Vec<_>>().join("\n")
+}
+
+// Separa por lo que no es letra ni número y por los cambios de minúscula a
+// mayúscula: "HTTPServer-error" da "HTTP", "Server" y "error".
+fn words(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase() || previous.is_numeric() || (previous.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn camel_case(line: &str) -> String {
+    words(line)
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let word = word.to_lowercase();
+            if i == 0 {
+                return word;
+            }
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(spec: &str, text: &str) -> String {
+        Pipeline::parse(spec, &BTreeMap::new()).unwrap().apply(text)
+    }
+
+    #[test]
+    fn cases() {
+        assert_eq!(apply("snake", "HTTPServer error-code\nuserId"), "http_server_error_code\nuser_id");
+        assert_eq!(apply("camel", "user_id\nHTTP server"), "userId\nhttpServer");
+        assert_eq!(apply("trim,upper", "  canción "), "CANCIÓN");
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(apply("sort,dedupe", "b\na\nb\nc"), "a\nb\nc");
+        assert_eq!(apply("dedupe", "b\na\nb"), "b\na");
+        assert_eq!(apply("join", " uno \n\n dos\n"), "uno dos");
+    }
+
+    #[test]
+    fn strip_ansi() {
+        assert_eq!(apply("strip-ansi", "\x1b[1;31merror\x1b[0m: \x1b]8;;http://x\x07link\x1b]8;;\x07"), "error: link");
+    }
+
+    #[test]
+    fn presets() {
+        let presets = BTreeMap::from([("slug".to_string(), vec!["trim".to_string(), "snake".to_string()])]);
+        let pipeline = Pipeline::parse("slug, upper", &presets).unwrap();
+        assert_eq!(pipeline.0, [Transform::Trim, Transform::Snake, Transform::Upper]);
+        assert!(Pipeline::parse("trim,nope", &presets).is_err());
+        assert!(Pipeline::parse("", &presets).is_err());
+    }
+}